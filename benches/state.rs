@@ -0,0 +1,75 @@
+//! Host-side comparison of the borsh and zero-copy paths for reading and
+//! updating a counter account. Compute units on-chain are measured by
+//! `tests/bench.ts`.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use development::state::{
+    ACCOUNT_LEN, AuthorityMode, Bounds, COUNTER_DISCRIMINATOR, Counter, CounterState, CounterValue,
+    LAYOUT_VERSION, OverflowPolicy,
+};
+use solana_program::pubkey::Pubkey;
+
+fn account() -> Vec<u8> {
+    let counter = Counter {
+        count: CounterValue::Unsigned(41),
+        authority: Pubkey::new_unique(),
+        version: 7,
+        policy: OverflowPolicy::Saturating,
+        bounds: Bounds::default(),
+        monotonic: false,
+        pending_authority: Pubkey::default(),
+        authority_mode: AuthorityMode::Authority,
+    };
+    let mut data = vec![0; ACCOUNT_LEN];
+    data[..8].copy_from_slice(&COUNTER_DISCRIMINATOR);
+    data[8] = LAYOUT_VERSION;
+    counter.store(&mut data).unwrap();
+
+    // Both paths must agree on the layout for the comparison to mean anything.
+    assert_eq!(Counter::load(&data).unwrap(), counter);
+    let state = CounterState::load(&data).unwrap();
+    assert_eq!(state.count(), counter.count);
+    assert_eq!(state.authority(), &counter.authority);
+    assert_eq!(state.version(), counter.version);
+    assert_eq!(state.policy(), counter.policy);
+    assert_eq!(state.bounds(), counter.bounds);
+    data
+}
+
+fn read(c: &mut Criterion) {
+    let data = account();
+    let mut group = c.benchmark_group("read");
+    group.bench_function("borsh", |b| {
+        b.iter(|| Counter::load(black_box(&data)).unwrap().count)
+    });
+    group.bench_function("zero_copy", |b| {
+        b.iter(|| CounterState::load(black_box(&data)).unwrap().count())
+    });
+    group.finish();
+}
+
+fn update(c: &mut Criterion) {
+    let mut data = account();
+    let mut group = c.benchmark_group("update");
+    group.bench_function("borsh", |b| {
+        b.iter(|| {
+            let mut counter = Counter::load(black_box(&data)).unwrap();
+            counter.count = counter.count.add(1, counter.policy).unwrap();
+            counter.version = counter.version.wrapping_add(1);
+            counter.store(black_box(&mut data)).unwrap();
+        })
+    });
+    group.bench_function("zero_copy", |b| {
+        b.iter(|| {
+            let state = CounterState::load_mut(black_box(&mut data)).unwrap();
+            state.set_count(state.count().add(1, state.policy()).unwrap());
+            state.set_version(state.version().wrapping_add(1));
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, read, update);
+criterion_main!(benches);
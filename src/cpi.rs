@@ -0,0 +1,655 @@
+//! Helpers for programs that call the counter through CPI. Enable the `cpi`
+//! feature, which also disables this crate's entrypoint.
+//!
+//! ```
+//! use development::cpi::{self, CpiContext, Update};
+//! use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, msg};
+//!
+//! fn count_visit<'info>(
+//!     program: AccountInfo<'info>,
+//!     counter: AccountInfo<'info>,
+//!     authority: AccountInfo<'info>,
+//! ) -> ProgramResult {
+//!     let ctx = CpiContext::new(program, Update { counter, authority });
+//!     let update = cpi::increment(ctx, 1)?;
+//!     msg!("Visit number {}", update.current);
+//!     Ok(())
+//! }
+//! ```
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::{get_return_data, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    ID,
+    instruction::{self, Deltas, Op},
+    state::{
+        AuthorityMode, Bounds, CounterKind, CounterReturnData, CounterValue, OverflowPolicy, Role,
+    },
+};
+
+/// The counter program account, the accounts of one instruction and the seeds
+/// of any PDA among them that the calling program signs for.
+/// `remaining_accounts` go where the instruction takes the signers of a
+/// multisig authority: after its own accounts, or before the counters of
+/// `batch` and `update_many`.
+pub struct CpiContext<'a, 'info, T> {
+    pub program: AccountInfo<'info>,
+    pub accounts: T,
+    pub signer_seeds: &'a [&'a [&'a [u8]]],
+    pub remaining_accounts: Vec<AccountInfo<'info>>
+}
+
+impl<'info, T> CpiContext<'_, 'info, T> {
+    pub fn new(program: AccountInfo<'info>, accounts: T) -> Self {
+        Self {
+            program,
+            accounts,
+            signer_seeds: &[],
+            remaining_accounts: Vec::new(),
+        }
+    }
+
+    pub fn with_remaining_accounts(mut self, remaining_accounts: Vec<AccountInfo<'info>>) -> Self {
+        self.remaining_accounts = remaining_accounts;
+        self
+    }
+}
+
+impl<'a, 'info, T> CpiContext<'a, 'info, T> {
+    pub fn new_with_signer(
+        program: AccountInfo<'info>,
+        accounts: T,
+        signer_seeds: &'a [&'a [&'a [u8]]],
+    ) -> Self {
+        Self {
+            program,
+            accounts,
+            signer_seeds,
+            remaining_accounts: Vec::new(),
+        }
+    }
+}
+
+pub struct Initialize<'info> {
+    pub payer: AccountInfo<'info>,
+    pub counter: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>
+}
+
+/// Accounts of every instruction that changes the counter's value or settings.
+pub struct Update<'info> {
+    pub counter: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>
+}
+
+pub struct Close<'info> {
+    pub counter: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub destination: AccountInfo<'info>
+}
+
+pub struct Migrate<'info> {
+    pub counter: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>
+}
+
+pub struct Get<'info> {
+    pub counter: AccountInfo<'info>
+}
+
+pub struct GrantRole<'info> {
+    pub counter: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>
+}
+
+pub struct SetMultisig<'info> {
+    pub counter: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub multisig: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>
+}
+
+pub struct Transfer<'info> {
+    pub source: AccountInfo<'info>,
+    pub destination: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>
+}
+
+/// Accounts of `Batch` and `UpdateMany`.
+pub struct Batch<'info> {
+    pub counters: Vec<AccountInfo<'info>>,
+    pub authority: AccountInfo<'info>
+}
+
+pub fn initialize<'info>(
+    ctx: CpiContext<'_, 'info, Initialize<'info>>,
+    seed: Vec<u8>,
+    kind: CounterKind,
+    policy: OverflowPolicy,
+    monotonic: bool,
+) -> ProgramResult {
+    let Initialize {
+        payer,
+        counter,
+        system_program,
+    } = &ctx.accounts;
+    invoke_counter(
+        &ctx,
+        instruction::initialize(payer.key, counter.key, seed, kind, policy, monotonic),
+        &[payer.clone(), counter.clone(), system_program.clone()],
+    )
+}
+
+pub fn increment<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    amount: u64,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(&ctx, instruction::increment(counter.key, authority.key, amount))
+}
+
+pub fn decrement<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    amount: u64,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(&ctx, instruction::decrement(counter.key, authority.key, amount))
+}
+
+pub fn reset<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(&ctx, instruction::reset(counter.key, authority.key))
+}
+
+pub fn set<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    value: CounterValue,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(&ctx, instruction::set(counter.key, authority.key, value))
+}
+
+pub fn compare_and_set<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    expected: CounterValue,
+    new: CounterValue,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(
+        &ctx,
+        instruction::compare_and_set(counter.key, authority.key, expected, new),
+    )
+}
+
+pub fn compare_version_and_set<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    version: u64,
+    new: CounterValue,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(
+        &ctx,
+        instruction::compare_version_and_set(counter.key, authority.key, version, new),
+    )
+}
+
+pub fn set_bounds<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    bounds: Bounds,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(&ctx, instruction::set_bounds(counter.key, authority.key, bounds))
+}
+
+pub fn propose_authority<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    new: Pubkey,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(&ctx, instruction::propose_authority(counter.key, authority.key, &new))
+}
+
+/// Accepts a pending authority transfer. `ctx.accounts.authority` is the
+/// pending authority.
+pub fn accept_authority<'info>(ctx: CpiContext<'_, 'info, Update<'info>>) -> ProgramResult {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_counter(
+        &ctx,
+        instruction::accept_authority(counter.key, authority.key),
+        &[counter.clone(), authority.clone()],
+    )
+}
+
+pub fn cancel_authority_transfer<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(&ctx, instruction::cancel_authority_transfer(counter.key, authority.key))
+}
+
+pub fn renounce_authority<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    mode: AuthorityMode,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_update(&ctx, instruction::renounce_authority(counter.key, authority.key, mode))
+}
+
+pub fn grant_role<'info>(
+    ctx: CpiContext<'_, 'info, GrantRole<'info>>,
+    member: Pubkey,
+    role: Role,
+) -> ProgramResult {
+    let GrantRole {
+        counter,
+        authority,
+        payer,
+        system_program,
+    } = &ctx.accounts;
+    invoke_counter(
+        &ctx,
+        instruction::grant_role(counter.key, authority.key, payer.key, &member, role),
+        &[
+            counter.clone(),
+            authority.clone(),
+            payer.clone(),
+            system_program.clone(),
+        ],
+    )
+}
+
+pub fn revoke_role<'info>(
+    ctx: CpiContext<'_, 'info, Update<'info>>,
+    member: Pubkey,
+    role: Role,
+) -> ProgramResult {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_counter(
+        &ctx,
+        instruction::revoke_role(counter.key, authority.key, &member, role),
+        &[counter.clone(), authority.clone()],
+    )
+}
+
+/// The new signers that sign, at least `threshold` of them, are passed as
+/// remaining accounts, after the approvers of a multisig authority if any.
+pub fn set_multisig<'info>(
+    ctx: CpiContext<'_, 'info, SetMultisig<'info>>,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+) -> ProgramResult {
+    let SetMultisig {
+        counter,
+        authority,
+        multisig,
+        payer,
+        system_program,
+    } = &ctx.accounts;
+    let account_infos = [
+        counter.clone(),
+        authority.clone(),
+        multisig.clone(),
+        payer.clone(),
+        system_program.clone(),
+    ];
+    let mut instruction =
+        instruction::set_multisig(counter.key, authority.key, payer.key, signers, threshold);
+    instruction.accounts.truncate(account_infos.len());
+    invoke_counter(&ctx, instruction, &account_infos)
+}
+
+pub fn close<'info>(ctx: CpiContext<'_, 'info, Close<'info>>) -> ProgramResult {
+    let Close {
+        counter,
+        authority,
+        destination,
+    } = &ctx.accounts;
+    let mut instruction = instruction::close(counter.key, authority.key, destination.key);
+    // A multisig authority must be writable to be closed with the counter.
+    instruction.accounts[1].is_writable = authority.is_writable;
+    invoke_counter(
+        &ctx,
+        instruction,
+        &[counter.clone(), authority.clone(), destination.clone()],
+    )
+}
+
+pub fn migrate<'info>(ctx: CpiContext<'_, 'info, Migrate<'info>>) -> ProgramResult {
+    let Migrate {
+        counter,
+        payer,
+        system_program,
+    } = &ctx.accounts;
+    invoke_counter(
+        &ctx,
+        instruction::migrate(counter.key, payer.key, counter.is_signer),
+        &[counter.clone(), payer.clone(), system_program.clone()],
+    )
+}
+
+pub fn get<'info>(
+    ctx: CpiContext<'_, 'info, Get<'info>>,
+) -> Result<CounterReturnData, ProgramError> {
+    let counter = &ctx.accounts.counter;
+    invoke_counter(&ctx, instruction::get(counter.key), std::slice::from_ref(counter))?;
+    read_return_data()
+}
+
+pub fn batch<'info>(ctx: CpiContext<'_, 'info, Batch<'info>>, ops: Vec<Op>) -> ProgramResult {
+    let Batch {
+        counters,
+        authority,
+    } = &ctx.accounts;
+    let keys: Vec<_> = counters.iter().map(|counter| *counter.key).collect();
+    let mut account_infos = counters.clone();
+    account_infos.push(authority.clone());
+    invoke_counter(
+        &ctx,
+        instruction::batch(&keys, authority.key, ops),
+        &account_infos,
+    )
+}
+
+pub fn update_many<'info>(
+    ctx: CpiContext<'_, 'info, Batch<'info>>,
+    deltas: Deltas,
+) -> ProgramResult {
+    let Batch {
+        counters,
+        authority,
+    } = &ctx.accounts;
+    let keys: Vec<_> = counters.iter().map(|counter| *counter.key).collect();
+    let mut account_infos = counters.clone();
+    account_infos.push(authority.clone());
+    invoke_counter(
+        &ctx,
+        instruction::update_many(&keys, authority.key, deltas),
+        &account_infos,
+    )
+}
+
+pub fn transfer<'info>(ctx: CpiContext<'_, 'info, Transfer<'info>>, amount: u64) -> ProgramResult {
+    let Transfer {
+        source,
+        destination,
+        authority,
+    } = &ctx.accounts;
+    invoke_counter(
+        &ctx,
+        instruction::transfer(source.key, destination.key, authority.key, amount),
+        &[source.clone(), destination.clone(), authority.clone()],
+    )
+}
+
+fn invoke_update<'info>(
+    ctx: &CpiContext<'_, 'info, Update<'info>>,
+    instruction: Instruction,
+) -> Result<CounterReturnData, ProgramError> {
+    let Update { counter, authority } = &ctx.accounts;
+    invoke_counter(ctx, instruction, &[counter.clone(), authority.clone()])?;
+    read_return_data()
+}
+
+fn invoke_counter<'info, T>(
+    ctx: &CpiContext<'_, 'info, T>,
+    instruction: Instruction,
+    account_infos: &[AccountInfo<'info>],
+) -> ProgramResult {
+    let (instruction, account_infos) = prepare_invoke(ctx, instruction, account_infos)?;
+    invoke_signed(&instruction, &account_infos, ctx.signer_seeds)
+}
+
+/// Adds the context's remaining accounts to `instruction` and collects the
+/// account infos to invoke it with. A multisig authority, which is owned by
+/// the counter program and cannot sign, is passed without signing.
+fn prepare_invoke<'info, T>(
+    ctx: &CpiContext<'_, 'info, T>,
+    mut instruction: Instruction,
+    account_infos: &[AccountInfo<'info>],
+) -> Result<(Instruction, Vec<AccountInfo<'info>>), ProgramError> {
+    if ctx.program.key != &ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    for meta in &mut instruction.accounts {
+        if account_infos
+            .iter()
+            .any(|acc| acc.key == &meta.pubkey && acc.owner == &ID && !acc.is_signer)
+        {
+            meta.is_signer = false;
+        }
+    }
+    let at = instruction::signers_index(&instruction);
+    instruction.accounts.splice(
+        at..at,
+        ctx.remaining_accounts.iter().map(|acc| AccountMeta {
+            pubkey: *acc.key,
+            is_signer: acc.is_signer,
+            is_writable: acc.is_writable,
+        }),
+    );
+    let mut account_infos = account_infos.to_vec();
+    account_infos.extend(ctx.remaining_accounts.iter().cloned());
+    account_infos.push(ctx.program.clone());
+    Ok((instruction, account_infos))
+}
+
+fn read_return_data() -> Result<CounterReturnData, ProgramError> {
+    match get_return_data() {
+        Some((program_id, data)) if program_id == ID => {
+            Ok(CounterReturnData::try_from_slice(&data)?)
+        }
+        _ => {
+            msg!("Counter program did not return data");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Backing storage of one test `AccountInfo`.
+    struct TestAccount {
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>
+    }
+
+    impl TestAccount {
+        fn new(owner: Pubkey) -> Self {
+            Self {
+                key: Pubkey::new_unique(),
+                owner,
+                lamports: 0,
+                data: Vec::new(),
+            }
+        }
+
+        fn info(&mut self, is_signer: bool, is_writable: bool) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                is_signer,
+                is_writable,
+                &mut self.lamports,
+                &mut self.data,
+                &self.owner,
+                false,
+                0,
+            )
+        }
+    }
+
+    fn meta(pubkey: Pubkey, is_signer: bool, is_writable: bool) -> AccountMeta {
+        AccountMeta {
+            pubkey,
+            is_signer,
+            is_writable,
+        }
+    }
+
+    #[test]
+    fn passes_update_accounts_then_the_program() {
+        let mut program = TestAccount::new(Pubkey::default());
+        program.key = ID;
+        let mut counter = TestAccount::new(ID);
+        let mut authority = TestAccount::new(Pubkey::default());
+        let (counter_key, authority_key) = (counter.key, authority.key);
+        let ctx = CpiContext::new(
+            program.info(false, false),
+            Update {
+                counter: counter.info(false, true),
+                authority: authority.info(true, false),
+            },
+        );
+
+        let Update { counter, authority } = &ctx.accounts;
+        let (instruction, account_infos) = prepare_invoke(
+            &ctx,
+            instruction::increment(counter.key, authority.key, 1),
+            &[counter.clone(), authority.clone()],
+        )
+        .unwrap();
+        assert_eq!(
+            instruction.accounts,
+            [meta(counter_key, false, true), meta(authority_key, true, false)]
+        );
+        let keys: Vec<_> = account_infos.iter().map(|acc| *acc.key).collect();
+        assert_eq!(keys, [counter_key, authority_key, ID]);
+    }
+
+    #[test]
+    fn unsigns_multisig_authorities_and_appends_remaining_accounts() {
+        let mut program = TestAccount::new(Pubkey::default());
+        program.key = ID;
+        let mut counter = TestAccount::new(ID);
+        let mut multisig = TestAccount::new(ID);
+        let mut signer = TestAccount::new(Pubkey::default());
+        let mut other = TestAccount::new(Pubkey::default());
+        let keys = [counter.key, multisig.key, signer.key, other.key];
+        let remaining = vec![signer.info(true, false), other.info(false, true)];
+        let ctx = CpiContext::new(
+            program.info(false, false),
+            Update {
+                counter: counter.info(false, true),
+                authority: multisig.info(false, false),
+            },
+        )
+        .with_remaining_accounts(remaining);
+
+        let Update { counter, authority } = &ctx.accounts;
+        let (instruction, account_infos) = prepare_invoke(
+            &ctx,
+            instruction::reset(counter.key, authority.key),
+            &[counter.clone(), authority.clone()],
+        )
+        .unwrap();
+        assert_eq!(
+            instruction.accounts,
+            [
+                meta(keys[0], false, true),
+                meta(keys[1], false, false),
+                meta(keys[2], true, false),
+                meta(keys[3], false, true),
+            ]
+        );
+        let infos: Vec<_> = account_infos.iter().map(|acc| *acc.key).collect();
+        assert_eq!(infos, [keys[0], keys[1], keys[2], keys[3], ID]);
+    }
+
+    #[test]
+    fn puts_remaining_accounts_before_batch_counters() {
+        let mut program = TestAccount::new(Pubkey::default());
+        program.key = ID;
+        let mut counter = TestAccount::new(ID);
+        let mut multisig = TestAccount::new(ID);
+        let mut signer = TestAccount::new(Pubkey::default());
+        let keys = [signer.key, counter.key, multisig.key];
+        let ctx = CpiContext::new(
+            program.info(false, false),
+            Batch {
+                counters: vec![counter.info(false, true)],
+                authority: multisig.info(false, false),
+            },
+        )
+        .with_remaining_accounts(vec![signer.info(true, false)]);
+
+        let Batch {
+            counters,
+            authority,
+        } = &ctx.accounts;
+        let deltas = Deltas::Same(instruction::Delta::Increment(1));
+        let (instruction, _) = prepare_invoke(
+            &ctx,
+            instruction::update_many(&[*counters[0].key], authority.key, deltas),
+            &[counters[0].clone(), authority.clone()],
+        )
+        .unwrap();
+        assert_eq!(
+            instruction.accounts,
+            [
+                meta(keys[0], true, false),
+                meta(keys[1], false, true),
+                meta(keys[2], false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_signing_authorities_that_are_not_program_owned() {
+        let mut program = TestAccount::new(Pubkey::default());
+        program.key = ID;
+        let mut counter = TestAccount::new(ID);
+        let mut pda = TestAccount::new(Pubkey::new_unique());
+        let ctx = CpiContext::new_with_signer(
+            program.info(false, false),
+            Update {
+                counter: counter.info(false, true),
+                authority: pda.info(false, false),
+            },
+            &[],
+        );
+
+        let Update { counter, authority } = &ctx.accounts;
+        let (instruction, _) = prepare_invoke(
+            &ctx,
+            instruction::increment(counter.key, authority.key, 1),
+            &[counter.clone(), authority.clone()],
+        )
+        .unwrap();
+        assert!(instruction.accounts[1].is_signer);
+    }
+
+    #[test]
+    fn rejects_other_programs() {
+        let mut program = TestAccount::new(Pubkey::default());
+        let mut counter = TestAccount::new(ID);
+        let ctx = CpiContext::new(
+            program.info(false, false),
+            Get {
+                counter: counter.info(false, false),
+            },
+        );
+
+        let counter = &ctx.accounts.counter;
+        let result =
+            prepare_invoke(&ctx, instruction::get(counter.key), std::slice::from_ref(counter));
+        assert_eq!(result.unwrap_err(), ProgramError::IncorrectProgramId);
+    }
+}
@@ -0,0 +1,73 @@
+use num_derive::FromPrimitive;
+use solana_program::{msg, program_error::ProgramError};
+use thiserror::Error;
+
+/// Errors returned by the counter program as `ProgramError::Custom` codes.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum CounterError {
+    #[error("Counter overflowed")]
+    Overflow,
+    #[error("Counter underflowed")]
+    Underflow,
+    #[error("Counter value does not match the expected value")]
+    ValueMismatch,
+    #[error("Counter version does not match the expected version")]
+    VersionMismatch,
+    #[error("Account is not a counter")]
+    InvalidAccountDiscriminator,
+    #[error("Counter account layout version is not supported")]
+    UnsupportedLayoutVersion,
+    #[error("Value does not match the counter's kind")]
+    KindMismatch,
+    #[error("Counter value is outside its bounds")]
+    OutOfBounds,
+    #[error("Counter bounds are invalid")]
+    InvalidBounds,
+    #[error("Monotonic counter cannot decrease")]
+    MonotonicViolation,
+    #[error("Transfer would change the total of the counters")]
+    TotalNotConserved,
+    #[error("Counter has no pending authority transfer")]
+    NoPendingAuthority,
+    #[error("Counter is immutable")]
+    ImmutableCounter,
+    #[error("Counter authority has been renounced")]
+    NoAuthority,
+    #[error("Signer does not hold the role this instruction requires")]
+    MissingRole,
+    #[error("Counter has no room for another role member")]
+    RoleTableFull,
+    #[error("Multisig signers or threshold are invalid")]
+    InvalidMultisig,
+    #[error("Multisig authority is missing required signatures")]
+    NotEnoughSigners,
+    #[error("Monotonic counter cannot be closed")]
+    MonotonicClose,
+}
+
+impl From<CounterError> for ProgramError {
+    fn from(e: CounterError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+#[allow(deprecated)]
+impl<T> solana_program::decode_error::DecodeError<T> for CounterError {
+    fn type_of() -> &'static str {
+        "CounterError"
+    }
+}
+
+#[allow(deprecated)]
+impl solana_program::program_error::PrintProgramError for CounterError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + solana_program::decode_error::DecodeError<E>
+            + solana_program::program_error::PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        msg!("Error: {}", self);
+    }
+}
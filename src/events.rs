@@ -0,0 +1,164 @@
+#[cfg(not(target_os = "solana"))]
+use base64::{Engine, engine::general_purpose::STANDARD};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, log::sol_log_data, pubkey::Pubkey};
+
+use crate::state::{AuthorityMode, CounterKind, CounterValue, Role};
+
+/// First field of every event logged by the program, so indexers can tell
+/// counter events apart from other `Program data:` lines.
+pub const EVENT_DISCRIMINATOR: [u8; 8] = *b"cntr_evt";
+
+/// Events emitted with `sol_log_data` as two fields: `EVENT_DISCRIMINATOR`
+/// and the borsh encoding of the event. Variants are only ever appended.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Eq)]
+pub enum CounterEvent {
+    CounterInitialized {
+        counter: Pubkey,
+        authority: Pubkey,
+        kind: CounterKind,
+    },
+    Incremented {
+        counter: Pubkey,
+        amount: u64,
+        previous: CounterValue,
+        current: CounterValue,
+    },
+    Decremented {
+        counter: Pubkey,
+        amount: u64,
+        previous: CounterValue,
+        current: CounterValue,
+    },
+    Reset {
+        counter: Pubkey,
+        previous: CounterValue,
+        current: CounterValue,
+    },
+    AuthorityChanged {
+        counter: Pubkey,
+        previous: Pubkey,
+        new: Pubkey,
+    },
+    Closed {
+        counter: Pubkey,
+        destination: Pubkey,
+        lamports: u64,
+    },
+    /// Emitted by `Set`, the compare-and-set instructions, and bound changes
+    /// that clamp the value.
+    ValueSet {
+        counter: Pubkey,
+        previous: CounterValue,
+        current: CounterValue,
+    },
+    AuthorityProposed {
+        counter: Pubkey,
+        authority: Pubkey,
+        pending: Pubkey,
+    },
+    AuthorityTransferCancelled {
+        counter: Pubkey,
+        pending: Pubkey,
+    },
+    AuthorityRenounced {
+        counter: Pubkey,
+        previous: Pubkey,
+        mode: AuthorityMode,
+    },
+    RoleGranted {
+        counter: Pubkey,
+        member: Pubkey,
+        role: Role,
+    },
+    RoleRevoked {
+        counter: Pubkey,
+        member: Pubkey,
+        role: Role,
+    },
+    MultisigSet {
+        counter: Pubkey,
+        multisig: Pubkey,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    },
+}
+
+impl CounterEvent {
+    pub(crate) fn emit(&self) -> ProgramResult {
+        let [discriminator, event] = self.log_fields()?;
+        sol_log_data(&[&discriminator, &event]);
+        Ok(())
+    }
+
+    /// The fields `emit` logs, which `decode` reads back.
+    fn log_fields(&self) -> Result<[Vec<u8>; 2], std::io::Error> {
+        Ok([EVENT_DISCRIMINATOR.to_vec(), borsh::to_vec(self)?])
+    }
+
+    /// Decodes the fields of a `sol_log_data` entry, returning `None` if they
+    /// are not a counter event.
+    pub fn decode(fields: &[&[u8]]) -> Option<Self> {
+        match fields {
+            [discriminator, event] if *discriminator == EVENT_DISCRIMINATOR => {
+                Self::try_from_slice(event).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Decodes a `Program data: <base64> <base64>` transaction log line. Only
+    /// available off-chain.
+    #[cfg(not(target_os = "solana"))]
+    pub fn from_log(line: &str) -> Option<Self> {
+        let fields = line
+            .strip_prefix("Program data: ")?
+            .split(' ')
+            .map(|field| STANDARD.decode(field).ok())
+            .collect::<Option<Vec<_>>>()?;
+        let fields: Vec<&[u8]> = fields.iter().map(Vec::as_slice).collect();
+        Self::decode(&fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incremented() -> CounterEvent {
+        CounterEvent::Incremented {
+            counter: Pubkey::new_unique(),
+            amount: 3,
+            previous: CounterValue::Unsigned(4),
+            current: CounterValue::Unsigned(7),
+        }
+    }
+
+    #[test]
+    fn decodes_emitted_fields() {
+        let event = incremented();
+        let [discriminator, data] = event.log_fields().unwrap();
+        assert_eq!(CounterEvent::decode(&[&discriminator, &data]), Some(event));
+    }
+
+    #[test]
+    fn decodes_log_lines() {
+        let event = incremented();
+        let [discriminator, data] = event.log_fields().unwrap();
+        let line = format!(
+            "Program data: {} {}",
+            STANDARD.encode(discriminator),
+            STANDARD.encode(data)
+        );
+        assert_eq!(CounterEvent::from_log(&line), Some(event));
+    }
+
+    #[test]
+    fn ignores_other_log_data() {
+        let data = borsh::to_vec(&incremented()).unwrap();
+        assert_eq!(CounterEvent::decode(&[b"other_ev", &data]), None);
+        assert_eq!(CounterEvent::decode(&[&EVENT_DISCRIMINATOR]), None);
+        assert_eq!(CounterEvent::from_log("Program log: Counter updated"), None);
+        assert_eq!(CounterEvent::from_log("Program data: not-base64"), None);
+    }
+}
@@ -0,0 +1,667 @@
+//! Instructions understood by the counter program and builders for them.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_system_interface::program as system_program;
+
+use crate::{
+    ID,
+    state::{
+        AuthorityMode, Bounds, CounterKind, CounterValue, OverflowPolicy, Role,
+        find_multisig_address,
+    },
+};
+
+/// Instructions of the counter program. Variants are only ever appended, so
+/// the borsh tag of existing instructions never changes.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Instructions {
+    Increment(u64),
+    Decrement(u64),
+    /// Creates a `kind` counter at the PDA `[COUNTER_SEED, payer, seed]`
+    /// with the payer as its authority. `seed` may be empty. Monotonic
+    /// counters can never go down or be closed.
+    Initialize {
+        seed: Vec<u8>,
+        kind: CounterKind,
+        policy: OverflowPolicy,
+        monotonic: bool,
+    },
+    /// Closes the counter and sends its lamports to the destination account.
+    /// If the authority is the counter's multisig, which must then be
+    /// writable, it is closed the same way. Fails for monotonic counters.
+    Close,
+    /// Sets the counter back to zero.
+    Reset,
+    /// Sets the counter to an absolute value of the counter's kind.
+    Set(CounterValue),
+    /// Sets the counter to `new` only if it currently holds `expected`.
+    CompareAndSet { expected: CounterValue, new: CounterValue },
+    /// Sets the counter to `new` only if its version is still `version`.
+    CompareVersionAndSet { version: u64, new: CounterValue },
+    /// Rewrites a counter from an older layout into the current one. Legacy
+    /// 4-byte accounts must sign and the payer becomes their authority.
+    Migrate,
+    /// Replaces the counter's bounds. The current value must satisfy them
+    /// or, in `Clamp` mode, is clamped into them.
+    SetBounds(Bounds),
+    /// Returns the counter through return data without writing to it. The
+    /// counter is the only account and need not be writable.
+    Get,
+    /// Applies `ops` in order to the listed counters, which are followed by
+    /// their common authority and preceded by its signers if it is a
+    /// multisig. Every op is checked before any counter is written, so
+    /// either the whole batch applies or none of it does. Each touched
+    /// counter's version is bumped once; no return data is set.
+    Batch(Vec<Op>),
+    /// Applies `Deltas` to every listed counter, with the same accounts and
+    /// all-or-nothing semantics as `Batch`.
+    UpdateMany(Deltas),
+    /// Moves `amount` from the source counter to the destination counter,
+    /// signed by an account that may decrement the source and increment the
+    /// destination. Fails if the two counters would not
+    /// add up to the same total afterwards, e.g. because a bound clamped one
+    /// of them. No return data is set.
+    Transfer { amount: u64 },
+    /// Proposes a new authority, which takes over once it signs
+    /// `AcceptAuthority`. Replaces any earlier proposal.
+    ProposeAuthority(Pubkey),
+    /// Makes the pending authority, which must sign instead of the current
+    /// authority, the counter's authority. Every admin role is revoked, so
+    /// the new authority starts with no admins it did not grant itself.
+    AcceptAuthority,
+    /// Withdraws the pending authority transfer.
+    CancelAuthorityTransfer,
+    /// Clears the authority for good. `Open` lets any signer change the
+    /// value; `Immutable` freezes the counter. `Authority` is rejected.
+    RenounceAuthority(AuthorityMode),
+    /// Grants `role` to `member`. Admins may grant the incrementer and
+    /// decrementer roles; only the authority may grant `Admin`. A new member
+    /// grows the account by `RoleEntry::LEN`, paid for by the payer.
+    GrantRole { member: Pubkey, role: Role },
+    /// Revokes `role` from `member`, with the same permissions as `GrantRole`.
+    RevokeRole { member: Pubkey, role: Role },
+    /// Makes the multisig at `find_multisig_address(counter)` the counter's
+    /// authority, creating it at the payer's expense if needed and revoking
+    /// every admin role, or replaces the signers and threshold of the
+    /// multisig that already is. At least `threshold` of the new `signers`
+    /// must sign, among the accounts after the system program, and none of
+    /// them may be the default pubkey, the counter or the multisig. From then
+    /// on the multisig account takes the authority's place without signing,
+    /// and at least `threshold` of `signers` follow the instruction's other
+    /// accounts as signers. `Batch` and `UpdateMany`, whose authority comes
+    /// last, take the signers before their counters instead.
+    SetMultisig { signers: Vec<Pubkey>, threshold: u8 }
+}
+
+/// One step of a `Batch`. `counter` indexes the batch's counter accounts.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Increment { counter: u8, amount: u64 },
+    Decrement { counter: u8, amount: u64 },
+    Set { counter: u8, value: CounterValue }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delta {
+    Increment(u64),
+    Decrement(u64)
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Deltas {
+    /// Applies one delta to every counter.
+    Same(Delta),
+    /// Applies the n-th delta to the n-th counter. There must be exactly one
+    /// delta per counter.
+    PerAccount(Vec<Delta>)
+}
+
+impl Op {
+    pub fn counter(&self) -> u8 {
+        match *self {
+            Self::Increment { counter, .. }
+            | Self::Decrement { counter, .. }
+            | Self::Set { counter, .. } => counter,
+        }
+    }
+}
+
+/// Creates the counter at `counter`, which must be
+/// `find_counter_address(payer, &seed)`.
+pub fn initialize(
+    payer: &Pubkey,
+    counter: &Pubkey,
+    seed: Vec<u8>,
+    kind: CounterKind,
+    policy: OverflowPolicy,
+    monotonic: bool,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &Instructions::Initialize {
+            seed,
+            kind,
+            policy,
+            monotonic,
+        },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*counter, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+pub fn increment(counter: &Pubkey, authority: &Pubkey, amount: u64) -> Instruction {
+    update(counter, authority, &Instructions::Increment(amount))
+}
+
+pub fn decrement(counter: &Pubkey, authority: &Pubkey, amount: u64) -> Instruction {
+    update(counter, authority, &Instructions::Decrement(amount))
+}
+
+pub fn reset(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    update(counter, authority, &Instructions::Reset)
+}
+
+pub fn set(counter: &Pubkey, authority: &Pubkey, value: CounterValue) -> Instruction {
+    update(counter, authority, &Instructions::Set(value))
+}
+
+pub fn compare_and_set(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    expected: CounterValue,
+    new: CounterValue,
+) -> Instruction {
+    update(counter, authority, &Instructions::CompareAndSet { expected, new })
+}
+
+pub fn compare_version_and_set(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    version: u64,
+    new: CounterValue,
+) -> Instruction {
+    update(counter, authority, &Instructions::CompareVersionAndSet { version, new })
+}
+
+pub fn set_bounds(counter: &Pubkey, authority: &Pubkey, bounds: Bounds) -> Instruction {
+    update(counter, authority, &Instructions::SetBounds(bounds))
+}
+
+/// Closes the counter and sends its lamports to `destination`.
+pub fn close(counter: &Pubkey, authority: &Pubkey, destination: &Pubkey) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &Instructions::Close,
+        vec![
+            AccountMeta::new(*counter, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*destination, false),
+        ],
+    )
+}
+
+/// Migrates the counter to the current layout. Legacy 4-byte counters must
+/// sign, so pass `counter_is_signer` for them.
+pub fn migrate(counter: &Pubkey, payer: &Pubkey, counter_is_signer: bool) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &Instructions::Migrate,
+        vec![
+            AccountMeta::new(*counter, counter_is_signer),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+pub fn get(counter: &Pubkey) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &Instructions::Get,
+        vec![AccountMeta::new_readonly(*counter, false)],
+    )
+}
+
+/// Applies `ops` to `counters`, all of which must have `authority`.
+pub fn batch(counters: &[Pubkey], authority: &Pubkey, ops: Vec<Op>) -> Instruction {
+    let mut accounts: Vec<AccountMeta> = counters
+        .iter()
+        .map(|counter| AccountMeta::new(*counter, false))
+        .collect();
+    accounts.push(AccountMeta::new_readonly(*authority, true));
+    Instruction::new_with_borsh(ID, &Instructions::Batch(ops), accounts)
+}
+
+/// Applies `deltas` to `counters`, all of which must have `authority`.
+pub fn update_many(counters: &[Pubkey], authority: &Pubkey, deltas: Deltas) -> Instruction {
+    let mut accounts: Vec<AccountMeta> = counters
+        .iter()
+        .map(|counter| AccountMeta::new(*counter, false))
+        .collect();
+    accounts.push(AccountMeta::new_readonly(*authority, true));
+    Instruction::new_with_borsh(ID, &Instructions::UpdateMany(deltas), accounts)
+}
+
+pub fn transfer(
+    source: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &Instructions::Transfer { amount },
+        vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+pub fn propose_authority(counter: &Pubkey, authority: &Pubkey, new: &Pubkey) -> Instruction {
+    update(counter, authority, &Instructions::ProposeAuthority(*new))
+}
+
+/// Accepts the authority transfer, signed by the pending authority.
+pub fn accept_authority(counter: &Pubkey, pending_authority: &Pubkey) -> Instruction {
+    update(counter, pending_authority, &Instructions::AcceptAuthority)
+}
+
+pub fn cancel_authority_transfer(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    update(counter, authority, &Instructions::CancelAuthorityTransfer)
+}
+
+pub fn renounce_authority(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    mode: AuthorityMode,
+) -> Instruction {
+    update(counter, authority, &Instructions::RenounceAuthority(mode))
+}
+
+pub fn grant_role(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    member: &Pubkey,
+    role: Role,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &Instructions::GrantRole {
+            member: *member,
+            role,
+        },
+        vec![
+            AccountMeta::new(*counter, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+pub fn revoke_role(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    member: &Pubkey,
+    role: Role,
+) -> Instruction {
+    update(
+        counter,
+        authority,
+        &Instructions::RevokeRole {
+            member: *member,
+            role,
+        },
+    )
+}
+
+/// Makes the counter's multisig its authority, with every new signer signing.
+/// If `authority` is already that multisig, pass the approving signers through
+/// `with_multisig_signers`.
+pub fn set_multisig(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+) -> Instruction {
+    let (multisig, _) = find_multisig_address(counter);
+    let mut accounts = vec![
+        AccountMeta::new(*counter, false),
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(multisig, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    accounts.extend(signers.iter().map(|signer| AccountMeta::new_readonly(*signer, true)));
+    Instruction::new_with_borsh(ID, &Instructions::SetMultisig { signers, threshold }, accounts)
+}
+
+/// Adapts an instruction built for a single-signer authority to `multisig`:
+/// the multisig no longer signs, and `signers` are added as signers where the
+/// instruction expects them. `Close` also takes the multisig as writable, to
+/// close it with the counter.
+pub fn with_multisig_signers(
+    mut instruction: Instruction,
+    multisig: &Pubkey,
+    signers: &[Pubkey],
+) -> Instruction {
+    let closes = matches!(Instructions::try_from_slice(&instruction.data), Ok(Instructions::Close));
+    for account in &mut instruction.accounts {
+        if account.pubkey == *multisig {
+            account.is_signer = false;
+            account.is_writable |= closes;
+        }
+    }
+    let at = signers_index(&instruction);
+    instruction.accounts.splice(
+        at..at,
+        signers.iter().map(|signer| AccountMeta::new_readonly(*signer, true)),
+    );
+    instruction
+}
+
+/// Where a multisig's signers go among `instruction`'s accounts: before the
+/// counters of `Batch` and `UpdateMany`, after the accounts of the rest.
+pub(crate) fn signers_index(instruction: &Instruction) -> usize {
+    match Instructions::try_from_slice(&instruction.data) {
+        Ok(Instructions::Batch(_) | Instructions::UpdateMany(_)) => 0,
+        _ => instruction.accounts.len(),
+    }
+}
+
+fn update(counter: &Pubkey, authority: &Pubkey, instruction: &Instructions) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        instruction,
+        vec![
+            AccountMeta::new(*counter, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{BoundsMode, find_multisig_address};
+
+    /// Checks that `instruction` targets this program, decodes to `expected`
+    /// and lists `accounts` in the order its processor reads them.
+    fn check(instruction: Instruction, expected: Instructions, accounts: &[AccountMeta]) {
+        assert_eq!(instruction.program_id, ID);
+        assert_eq!(Instructions::try_from_slice(&instruction.data).unwrap(), expected);
+        assert_eq!(instruction.accounts, accounts);
+    }
+
+    fn update_accounts(counter: Pubkey, authority: Pubkey) -> [AccountMeta; 2] {
+        [AccountMeta::new(counter, false), AccountMeta::new_readonly(authority, true)]
+    }
+
+    #[test]
+    fn initialize_is_paid_by_the_payer() {
+        let (payer, counter) = (Pubkey::new_unique(), Pubkey::new_unique());
+        check(
+            initialize(
+                &payer,
+                &counter,
+                b"seed".to_vec(),
+                CounterKind::Signed,
+                OverflowPolicy::Wrapping,
+                true,
+            ),
+            Instructions::Initialize {
+                seed: b"seed".to_vec(),
+                kind: CounterKind::Signed,
+                policy: OverflowPolicy::Wrapping,
+                monotonic: true,
+            },
+            &[
+                AccountMeta::new(payer, true),
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn updates_take_the_counter_and_its_authority() {
+        let (counter, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let new = Pubkey::new_unique();
+        let value = CounterValue::Unsigned(5);
+        let bounds = Bounds {
+            mode: BoundsMode::Reject,
+            min: CounterValue::Unsigned(1),
+            max: CounterValue::Unsigned(9),
+        };
+        let cases = [
+            (increment(&counter, &authority, 3), Instructions::Increment(3)),
+            (decrement(&counter, &authority, 2), Instructions::Decrement(2)),
+            (reset(&counter, &authority), Instructions::Reset),
+            (set(&counter, &authority, value), Instructions::Set(value)),
+            (
+                compare_and_set(&counter, &authority, value, CounterValue::Unsigned(6)),
+                Instructions::CompareAndSet {
+                    expected: value,
+                    new: CounterValue::Unsigned(6),
+                },
+            ),
+            (
+                compare_version_and_set(&counter, &authority, 7, value),
+                Instructions::CompareVersionAndSet {
+                    version: 7,
+                    new: value,
+                },
+            ),
+            (set_bounds(&counter, &authority, bounds), Instructions::SetBounds(bounds)),
+            (propose_authority(&counter, &authority, &new), Instructions::ProposeAuthority(new)),
+            (accept_authority(&counter, &authority), Instructions::AcceptAuthority),
+            (
+                cancel_authority_transfer(&counter, &authority),
+                Instructions::CancelAuthorityTransfer,
+            ),
+            (
+                renounce_authority(&counter, &authority, AuthorityMode::Open),
+                Instructions::RenounceAuthority(AuthorityMode::Open),
+            ),
+            (
+                revoke_role(&counter, &authority, &new, Role::Incrementer),
+                Instructions::RevokeRole {
+                    member: new,
+                    role: Role::Incrementer,
+                },
+            ),
+        ];
+        for (instruction, expected) in cases {
+            check(instruction, expected, &update_accounts(counter, authority));
+        }
+    }
+
+    #[test]
+    fn close_sends_lamports_to_the_destination() {
+        let (counter, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let destination = Pubkey::new_unique();
+        check(
+            close(&counter, &authority, &destination),
+            Instructions::Close,
+            &[
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(destination, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn migrate_signs_with_legacy_counters_only() {
+        let (counter, payer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for counter_is_signer in [false, true] {
+            check(
+                migrate(&counter, &payer, counter_is_signer),
+                Instructions::Migrate,
+                &[
+                    AccountMeta::new(counter, counter_is_signer),
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+            );
+        }
+    }
+
+    #[test]
+    fn get_only_reads_the_counter() {
+        let counter = Pubkey::new_unique();
+        check(get(&counter), Instructions::Get, &[AccountMeta::new_readonly(counter, false)]);
+    }
+
+    #[test]
+    fn batches_list_counters_before_the_authority() {
+        let counters = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let authority = Pubkey::new_unique();
+        let accounts = [
+            AccountMeta::new(counters[0], false),
+            AccountMeta::new(counters[1], false),
+            AccountMeta::new_readonly(authority, true),
+        ];
+        let ops = vec![
+            Op::Increment {
+                counter: 1,
+                amount: 4,
+            },
+            Op::Set {
+                counter: 0,
+                value: CounterValue::Signed(-1),
+            },
+        ];
+        check(batch(&counters, &authority, ops.clone()), Instructions::Batch(ops), &accounts);
+        let deltas = Deltas::PerAccount(vec![Delta::Increment(1), Delta::Decrement(2)]);
+        check(
+            update_many(&counters, &authority, deltas.clone()),
+            Instructions::UpdateMany(deltas),
+            &accounts,
+        );
+    }
+
+    #[test]
+    fn transfer_takes_both_counters_and_the_authority() {
+        let (source, destination) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let authority = Pubkey::new_unique();
+        check(
+            transfer(&source, &destination, &authority, 8),
+            Instructions::Transfer { amount: 8 },
+            &[
+                AccountMeta::new(source, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(authority, true),
+            ],
+        );
+    }
+
+    #[test]
+    fn grant_role_is_paid_by_the_payer() {
+        let (counter, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (payer, member) = (Pubkey::new_unique(), Pubkey::new_unique());
+        check(
+            grant_role(&counter, &authority, &payer, &member, Role::Admin),
+            Instructions::GrantRole {
+                member,
+                role: Role::Admin,
+            },
+            &[
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn set_multisig_targets_the_counter_multisig_and_signs_with_its_signers() {
+        let (counter, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let payer = Pubkey::new_unique();
+        let signers = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let (multisig, _) = find_multisig_address(&counter);
+        check(
+            set_multisig(&counter, &authority, &payer, signers.clone(), 2),
+            Instructions::SetMultisig {
+                signers: signers.clone(),
+                threshold: 2,
+            },
+            &[
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(multisig, false),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(signers[0], true),
+                AccountMeta::new_readonly(signers[1], true),
+            ],
+        );
+    }
+
+    #[test]
+    fn multisig_signers_replace_the_authority_signature() {
+        let counter = Pubkey::new_unique();
+        let (multisig, _) = find_multisig_address(&counter);
+        let signers = [Pubkey::new_unique(), Pubkey::new_unique()];
+        check(
+            with_multisig_signers(increment(&counter, &multisig, 1), &multisig, &signers),
+            Instructions::Increment(1),
+            &[
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(multisig, false),
+                AccountMeta::new_readonly(signers[0], true),
+                AccountMeta::new_readonly(signers[1], true),
+            ],
+        );
+    }
+
+    #[test]
+    fn multisig_signers_come_before_batch_counters() {
+        let counters = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let (multisig, _) = find_multisig_address(&counters[0]);
+        let signer = Pubkey::new_unique();
+        let ops = vec![Op::Increment {
+            counter: 1,
+            amount: 2,
+        }];
+        check(
+            with_multisig_signers(batch(&counters, &multisig, ops.clone()), &multisig, &[signer]),
+            Instructions::Batch(ops),
+            &[
+                AccountMeta::new_readonly(signer, true),
+                AccountMeta::new(counters[0], false),
+                AccountMeta::new(counters[1], false),
+                AccountMeta::new_readonly(multisig, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn close_takes_the_multisig_as_writable() {
+        let (counter, destination) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (multisig, _) = find_multisig_address(&counter);
+        let signer = Pubkey::new_unique();
+        check(
+            with_multisig_signers(close(&counter, &multisig, &destination), &multisig, &[signer]),
+            Instructions::Close,
+            &[
+                AccountMeta::new(counter, false),
+                AccountMeta::new(multisig, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(signer, true),
+            ],
+        );
+    }
+}
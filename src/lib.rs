@@ -1,41 +1,1265 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshDeserialize;
+use error::CounterError;
+use events::CounterEvent;
+use instruction::{Delta, Deltas, Instructions, Op};
 use solana_program::{
     account_info::{AccountInfo, next_account_info},
-    entrypoint,
+    declare_id,
     entrypoint::ProgramResult,
     msg,
-    pubkey::Pubkey,
+    program::{invoke, invoke_signed, set_return_data},
+    program_error::ProgramError,
+    pubkey::{MAX_SEED_LEN, Pubkey},
+    rent::Rent,
+    sysvar::Sysvar,
 };
+use solana_system_interface::{instruction as system_instruction, program as system_program};
+use state::{
+    ACCOUNT_LEN, AccountHeader, AuthorityMode, Bounds, COUNTER_SEED, Counter, CounterKind,
+    CounterReturnData, CounterState, CounterV1, CounterValue, LAYOUT_VERSION, LEGACY_LEN,
+    MAX_ROLE_MEMBERS, MULTISIG_SEED, Multisig, OverflowPolicy, Role, RoleEntry,
+    create_multisig_address, find_counter_address, find_multisig_address, has_role, role_entries,
+    role_entries_mut,
+};
+
+#[cfg(feature = "cpi")]
+pub mod cpi;
+pub mod error;
+pub mod events;
+pub mod instruction;
+pub mod state;
+
+declare_id!("CC6Jc1wkfdyyiRGQAGy8UVXXZdb9LDRbc7hJnrxdC44U");
 
-#[derive(BorshSerialize, BorshDeserialize)]
-enum Instructions {
-    Increment(u32),
-    Decrement(u32)
+/// Outcome of `process_update`, used to build the instruction's event.
+struct Update {
+    counter: Pubkey,
+    authority: Pubkey,
+    previous: CounterValue,
+    current: CounterValue
+}
+
+impl Update {
+    fn value_set(&self) -> CounterEvent {
+        CounterEvent::ValueSet {
+            counter: self.counter,
+            previous: self.previous,
+            current: self.current,
+        }
+    }
 }
 
-#[derive(BorshDeserialize, BorshSerialize)]
-struct Counter {
-    count: u32
+/// What `check_signer` requires of the account signing for a counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Permission {
+    /// The authority alone.
+    Authority,
+    /// The authority or an admin, to change the counter's settings.
+    Admin,
+    /// The authority or a holder of the role, to change the counter's value.
+    /// Also granted to any signer once the authority is renounced in `Open`
+    /// mode.
+    Value(Role)
 }
 
-entrypoint!(process_instructions);
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instructions);
 
 pub fn process_instructions(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
+    #[allow(deprecated)]
+    process(program_id, accounts, instruction_data).inspect_err(|error| {
+        use solana_program::program_error::PrintProgramError;
+        error.print::<CounterError>()
+    })
+}
+
+fn process(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    if program_id != &ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    match Instructions::try_from_slice(instruction_data)? {
+        Instructions::Increment(amount) => {
+            let permission = Permission::Value(Role::Incrementer);
+            let update = process_update(program_id, accounts, permission, |counter_data| {
+                msg!("Adding {} with {:?} policy", amount, counter_data.policy());
+                let count = counter_data.count().add(amount, counter_data.policy())?;
+                counter_data.set_count(count);
+                Ok(())
+            })?;
+            CounterEvent::Incremented {
+                counter: update.counter,
+                amount,
+                previous: update.previous,
+                current: update.current,
+            }
+            .emit()
+        }
+        Instructions::Decrement(amount) => {
+            let permission = Permission::Value(Role::Decrementer);
+            let update = process_update(program_id, accounts, permission, |counter_data| {
+                counter_data.check_not_monotonic()?;
+                msg!("Subtracting {} with {:?} policy", amount, counter_data.policy());
+                let count = counter_data.count().sub(amount, counter_data.policy())?;
+                counter_data.set_count(count);
+                Ok(())
+            })?;
+            CounterEvent::Decremented {
+                counter: update.counter,
+                amount,
+                previous: update.previous,
+                current: update.current,
+            }
+            .emit()
+        }
+        Instructions::Initialize {
+            seed,
+            kind,
+            policy,
+            monotonic,
+        } => process_initialize(program_id, accounts, &seed, kind, policy, monotonic),
+        Instructions::Close => process_close(program_id, accounts),
+        Instructions::Get => process_get(program_id, accounts),
+        Instructions::Batch(ops) => process_batch(program_id, accounts, &ops),
+        Instructions::UpdateMany(deltas) => process_update_many(program_id, accounts, deltas),
+        Instructions::Transfer { amount } => process_transfer(program_id, accounts, amount),
+        Instructions::ProposeAuthority(new) => {
+            if new == Pubkey::default() {
+                msg!("Cannot propose the default pubkey as authority");
+                return Err(ProgramError::InvalidArgument);
+            }
+            let update =
+                process_update(program_id, accounts, Permission::Authority, |counter_data| {
+                    msg!("Authority {} proposed", new);
+                    counter_data.set_pending_authority(Some(new));
+                    Ok(())
+                })?;
+            CounterEvent::AuthorityProposed {
+                counter: update.counter,
+                authority: update.authority,
+                pending: new,
+            }
+            .emit()
+        }
+        Instructions::AcceptAuthority => process_accept_authority(program_id, accounts),
+        Instructions::CancelAuthorityTransfer => {
+            let mut pending = Pubkey::default();
+            let update =
+                process_update(program_id, accounts, Permission::Authority, |counter_data| {
+                    pending = *counter_data
+                        .pending_authority()
+                        .ok_or(CounterError::NoPendingAuthority)?;
+                    msg!("Transfer to authority {} cancelled", pending);
+                    counter_data.set_pending_authority(None);
+                    Ok(())
+                })?;
+            CounterEvent::AuthorityTransferCancelled {
+                counter: update.counter,
+                pending,
+            }
+            .emit()
+        }
+        Instructions::RenounceAuthority(mode) => {
+            if mode == AuthorityMode::Authority {
+                msg!("Renouncing requires the Open or Immutable mode");
+                return Err(ProgramError::InvalidArgument);
+            }
+            let update =
+                process_update(program_id, accounts, Permission::Authority, |counter_data| {
+                    msg!(
+                        "Authority {} renounced, counter is now {:?}",
+                        counter_data.authority(),
+                        mode
+                    );
+                    counter_data.set_authority(Pubkey::default());
+                    counter_data.set_pending_authority(None);
+                    counter_data.set_authority_mode(mode);
+                    Ok(())
+                })?;
+            CounterEvent::AuthorityRenounced {
+                counter: update.counter,
+                previous: update.authority,
+                mode,
+            }
+            .emit()
+        }
+        Instructions::GrantRole { member, role } => {
+            process_grant_role(program_id, accounts, member, role)
+        }
+        Instructions::RevokeRole { member, role } => {
+            process_revoke_role(program_id, accounts, member, role)
+        }
+        Instructions::SetMultisig { signers, threshold } => {
+            process_set_multisig(program_id, accounts, &signers, threshold)
+        }
+        Instructions::Migrate => process_migrate(program_id, accounts),
+        Instructions::Reset => {
+            let permission = Permission::Value(Role::Admin);
+            let update = process_update(program_id, accounts, permission, |counter_data| {
+                counter_data.check_not_monotonic()?;
+                msg!("Counter reset, previous value {}", counter_data.count());
+                counter_data.set_count(CounterValue::zero(counter_data.count().kind()));
+                Ok(())
+            })?;
+            CounterEvent::Reset {
+                counter: update.counter,
+                previous: update.previous,
+                current: update.current,
+            }
+            .emit()
+        }
+        Instructions::Set(value) => {
+            process_update(program_id, accounts, Permission::Value(Role::Admin), |counter_data| {
+                counter_data.count().check_kind(value)?;
+                msg!("Counter set to {}, previous value {}", value, counter_data.count());
+                counter_data.set_count(value);
+                Ok(())
+            })?
+            .value_set()
+            .emit()
+        }
+        Instructions::SetBounds(bounds) => {
+            let update = process_update(program_id, accounts, Permission::Admin, |counter_data| {
+                bounds.validate(counter_data.count().kind())?;
+                msg!("Counter bounds set to {:?}, previous {:?}", bounds, counter_data.bounds());
+                counter_data.set_bounds(bounds);
+                Ok(())
+            })?;
+            if update.previous != update.current {
+                update.value_set().emit()?;
+            }
+            Ok(())
+        }
+        Instructions::CompareAndSet { expected, new } => {
+            process_update(program_id, accounts, Permission::Value(Role::Admin), |counter_data| {
+                counter_data.count().check_kind(expected)?;
+                counter_data.count().check_kind(new)?;
+                if counter_data.count() != expected {
+                    msg!("Expected value {}, found {}", expected, counter_data.count());
+                    return Err(CounterError::ValueMismatch.into());
+                }
+                counter_data.set_count(new);
+                Ok(())
+            })?
+            .value_set()
+            .emit()
+        }
+        Instructions::CompareVersionAndSet { version, new } => {
+            process_update(program_id, accounts, Permission::Value(Role::Admin), |counter_data| {
+                counter_data.count().check_kind(new)?;
+                if counter_data.version() != version {
+                    msg!("Expected version {}, found {}", version, counter_data.version());
+                    return Err(CounterError::VersionMismatch.into());
+                }
+                counter_data.set_count(new);
+                Ok(())
+            })?
+            .value_set()
+            .emit()
+        }
+    }
+}
+
+fn process_initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    seed: &[u8],
+    kind: CounterKind,
+    policy: OverflowPolicy,
+    monotonic: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let acc = next_account_info(accounts_iter)?;
+    let system_program_acc = next_account_info(accounts_iter)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_acc.key != &system_program::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if seed.len() > MAX_SEED_LEN {
+        return Err(ProgramError::MaxSeedLengthExceeded);
+    }
+
+    let (expected, bump) = find_counter_address(payer.key, seed);
+    if acc.key != &expected {
+        msg!("Expected counter address {}, got {}", expected, acc.key);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if acc.owner == program_id {
+        msg!("Counter {} is already initialized", acc.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let signer_seeds: &[&[u8]] = &[COUNTER_SEED, payer.key.as_ref(), seed, &[bump]];
+    create_pda_account(payer, acc, system_program_acc, program_id, ACCOUNT_LEN, signer_seeds)?;
+
+    let counter_data = Counter {
+        count: CounterValue::zero(kind),
+        authority: *payer.key,
+        version: 0,
+        policy,
+        bounds: Bounds::default(),
+        monotonic,
+        pending_authority: Pubkey::default(),
+        authority_mode: AuthorityMode::Authority,
+    };
+    let mut data = acc.data.borrow_mut();
+    AccountHeader::new(bump).store(&mut data)?;
+    counter_data.store(&mut data)?;
+
+    CounterEvent::CounterInitialized {
+        counter: *acc.key,
+        authority: *payer.key,
+        kind,
+    }
+    .emit()?;
+    msg!(
+        "{:?} counter {} initialized for {} with {:?} policy, monotonic: {}",
+        kind,
+        acc.key,
+        payer.key,
+        policy,
+        monotonic
+    );
+    Ok(())
+}
+
+/// Drains the counter into `destination`, then shrinks it to zero bytes and
+/// hands it back to the system program so that lamports sent to it later in
+/// the same transaction cannot revive the old counter state. Monotonic
+/// counters stay open for good: `Initialize` would recreate them at zero. A
+/// counter whose authority is its own multisig closes the multisig too, which
+/// nothing else could approve for once the counter is gone.
+fn process_close(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let acc = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    let destination = next_account_info(accounts_iter)?;
+    check_counter_account(program_id, acc)?;
+
+    let data = acc.data.borrow();
+    check_signer(&data, authority, Permission::Authority, accounts_iter.as_slice())?;
+    if CounterState::load(&data)?.monotonic() {
+        msg!("Monotonic counter {} cannot be closed", acc.key);
+        return Err(CounterError::MonotonicClose.into());
+    }
+    drop(data);
+
+    if destination.key == acc.key {
+        msg!("Cannot close counter {} into itself", acc.key);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if is_own_multisig(acc.key, authority)? {
+        if destination.key == authority.key {
+            msg!("Cannot close counter {} into its multisig", acc.key);
+            return Err(ProgramError::InvalidArgument);
+        }
+        if !authority.is_writable {
+            msg!("Multisig {} must be writable to be closed with the counter", authority.key);
+            return Err(ProgramError::Immutable);
+        }
+        let lamports = close_account(authority, destination)?;
+        msg!(
+            "Multisig {} closed, {} lamports sent to {}",
+            authority.key,
+            lamports,
+            destination.key
+        );
+    }
+    let lamports = close_account(acc, destination)?;
+
+    CounterEvent::Closed {
+        counter: *acc.key,
+        destination: *destination.key,
+        lamports,
+    }
+    .emit()?;
+    msg!("Counter {} closed, {} lamports sent to {}", acc.key, lamports, destination.key);
+    Ok(())
+}
+
+/// Whether `authority` is the multisig at `find_multisig_address(counter)`,
+/// rather than a keypair or another counter's multisig.
+fn is_own_multisig(counter: &Pubkey, authority: &AccountInfo) -> Result<bool, ProgramError> {
+    if authority.owner != &ID {
+        return Ok(false);
+    }
+    let bump = Multisig::load(&authority.data.borrow())?.bump();
+    let address = create_multisig_address(counter, bump).map_err(|_| ProgramError::InvalidSeeds)?;
+    Ok(address == *authority.key)
+}
+
+/// Moves all of `acc`'s lamports to `destination` and hands the emptied
+/// account back to the system program. Returns the lamports moved.
+fn close_account(acc: &AccountInfo, destination: &AccountInfo) -> Result<u64, ProgramError> {
+    let lamports = acc.lamports();
+    **destination.lamports.borrow_mut() = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **acc.lamports.borrow_mut() = 0;
+
+    acc.data.borrow_mut().fill(0);
+    acc.resize(0)?;
+    acc.assign(&system_program::ID);
+    Ok(lamports)
+}
+
+/// Upgrades legacy 4-byte counters and older versioned layouts in place.
+/// Counters already on `LAYOUT_VERSION` are left untouched.
+fn process_migrate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let acc = next_account_info(accounts_iter)?;
+    let payer = next_account_info(accounts_iter)?;
+    let system_program_acc = next_account_info(accounts_iter)?;
+    check_counter_account(program_id, acc)?;
+
+    let legacy = acc.data_len() == LEGACY_LEN;
+    let (bump, counter_data) = if legacy {
+        // Legacy counters are keypair accounts owned by this program but with
+        // no authority recorded, so the counter's own signature is what
+        // proves the payer may claim it. They are not PDAs, so there is no
+        // bump.
+        if !acc.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let count = u32::try_from_slice(&acc.data.borrow())?;
+        let counter_data = Counter {
+            count: CounterValue::Unsigned(count.into()),
+            authority: *payer.key,
+            version: 0,
+            policy: OverflowPolicy::default(),
+            bounds: Bounds::default(),
+            monotonic: false,
+            pending_authority: Pubkey::default(),
+            authority_mode: AuthorityMode::Authority,
+        };
+        (0, counter_data)
+    } else {
+        let data = acc.data.borrow();
+        let mut data = &data[..];
+        let header = AccountHeader::load(&mut data)?;
+        match header.layout_version {
+            LAYOUT_VERSION => {
+                msg!("Counter {} already uses layout version {}", acc.key, LAYOUT_VERSION);
+                return Ok(());
+            }
+            1 => {
+                let v1 = CounterV1::deserialize(&mut data)?;
+                let counter_data = Counter {
+                    count: CounterValue::Unsigned(v1.count.into()),
+                    authority: v1.authority,
+                    version: v1.version,
+                    policy: OverflowPolicy::default(),
+                    bounds: Bounds::default(),
+                    monotonic: false,
+                    pending_authority: Pubkey::default(),
+                    authority_mode: AuthorityMode::Authority,
+                };
+                (header.bump, counter_data)
+            }
+            layout_version => {
+                msg!("Unknown counter layout version {}", layout_version);
+                return Err(CounterError::UnsupportedLayoutVersion.into());
+            }
+        }
+    };
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if system_program_acc.key != &system_program::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let top_up = Rent::get()?
+        .minimum_balance(ACCOUNT_LEN)
+        .saturating_sub(acc.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, acc.key, top_up),
+            &[payer.clone(), acc.clone(), system_program_acc.clone()],
+        )?;
+    }
+    acc.resize(ACCOUNT_LEN)?;
+
+    let mut data = acc.data.borrow_mut();
+    data.fill(0);
+    AccountHeader::new(bump).store(&mut data)?;
+    counter_data.store(&mut data)?;
+
+    if legacy {
+        CounterEvent::AuthorityChanged {
+            counter: *acc.key,
+            previous: Pubkey::default(),
+            new: counter_data.authority,
+        }
+        .emit()?;
+    }
+    msg!(
+        "Counter {} migrated to layout version {} with count {}",
+        acc.key,
+        LAYOUT_VERSION,
+        counter_data.count
+    );
+    Ok(())
+}
+
+fn process_get(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let acc = next_account_info(&mut accounts.iter())?;
-    let instruction = Instructions::try_from_slice(instruction_data)?;
-    let mut counter_data = Counter::try_from_slice(&acc.data.borrow())?;
+    check_counter_owner(program_id, acc)?;
+
+    let data = acc.data.borrow();
+    let counter_data = CounterState::load(&data)?;
+    let return_data = CounterReturnData {
+        previous: counter_data.count(),
+        current: counter_data.count(),
+        version: counter_data.version(),
+    };
+    set_return_data(&borsh::to_vec(&return_data)?);
+
+    msg!("Counter is {} (version {})", counter_data.count(), counter_data.version());
+    Ok(())
+}
+
+/// Loads the counter, lets a signer with `permission` apply `update` to it and
+/// stores the result.
+fn process_update<F>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    permission: Permission,
+    update: F,
+) -> Result<Update, ProgramError>
+where
+    F: FnOnce(&mut CounterState) -> ProgramResult,
+{
+    let accounts_iter = &mut accounts.iter();
+    let acc = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    check_counter_account(program_id, acc)?;
+
+    let mut data = acc.data.borrow_mut();
+    check_signer(&data, authority, permission, accounts_iter.as_slice())?;
+    let counter_data = CounterState::load_mut(&mut data)?;
+
+    let previous = counter_data.count();
+    update(counter_data)?;
+    let current = check_update(counter_data, previous, counter_data.count())?;
+    let version = counter_data.version().wrapping_add(1);
+    counter_data.set_count(current);
+    counter_data.set_version(version);
+
+    let return_data = CounterReturnData {
+        previous,
+        current,
+        version,
+    };
+    set_return_data(&borsh::to_vec(&return_data)?);
+
+    msg!("Counter updated to {} (version {})", current, version);
+    Ok(Update {
+        counter: *acc.key,
+        authority: *authority.key,
+        previous,
+        current,
+    })
+}
 
-    match instruction {
-        Instructions::Increment(value) => counter_data.count += value,
-        Instructions::Decrement(value) => counter_data.count -= value
+/// Splits the accounts of `Batch` and `UpdateMany` into the leading signers
+/// of a multisig authority, the counters, and the authority that ends them.
+/// Counters are owned by this program, so the signers are exactly the signing
+/// accounts that come before the first program-owned one.
+fn split_batch_accounts<'a, 'info>(
+    program_id: &Pubkey,
+    accounts: &'a [AccountInfo<'info>],
+) -> Result<BatchAccounts<'a, 'info>, ProgramError> {
+    let (authority, rest) = accounts
+        .split_last()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let signers = rest
+        .iter()
+        .take_while(|acc| acc.is_signer && acc.owner != program_id)
+        .count();
+    let (cosigners, counters) = rest.split_at(signers);
+    Ok((cosigners, counters, authority))
+}
+
+type BatchAccounts<'a, 'info> = (
+    &'a [AccountInfo<'info>],
+    &'a [AccountInfo<'info>],
+    &'a AccountInfo<'info>,
+);
+
+/// Applies every op to an in-memory copy of the counters first, so that the
+/// counters are only written once the whole batch is known to succeed.
+fn process_batch(program_id: &Pubkey, accounts: &[AccountInfo], ops: &[Op]) -> ProgramResult {
+    let (cosigners, counters, authority) = split_batch_accounts(program_id, accounts)?;
+
+    let mut counts = Vec::with_capacity(counters.len());
+    for (i, acc) in counters.iter().enumerate() {
+        check_counter_account(program_id, acc)?;
+        if counters[..i].iter().any(|other| other.key == acc.key) {
+            msg!("Counter {} is listed more than once", acc.key);
+            return Err(ProgramError::InvalidArgument);
+        }
+        counts.push(CounterState::load(&acc.data.borrow())?.count());
+    }
+
+    let mut touched = vec![false; counters.len()];
+    let mut events = Vec::with_capacity(ops.len());
+    for op in ops {
+        let index = usize::from(op.counter());
+        let Some(acc) = counters.get(index) else {
+            msg!("Op refers to counter {}, but only {} were given", index, counters.len());
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        let data = acc.data.borrow();
+        check_signer(&data, authority, Permission::Value(op_role(op)), cosigners)?;
+        let counter_data = CounterState::load(&data)?;
+        let previous = counts[index];
+        let current = match *op {
+            Op::Increment { amount, .. } => previous.add(amount, counter_data.policy())?,
+            Op::Decrement { amount, .. } => {
+                counter_data.check_not_monotonic()?;
+                previous.sub(amount, counter_data.policy())?
+            }
+            Op::Set { value, .. } => {
+                previous.check_kind(value)?;
+                value
+            }
+        };
+        let current = check_update(counter_data, previous, current)?;
+        counts[index] = current;
+        touched[index] = true;
+
+        let counter = *acc.key;
+        events.push(match *op {
+            Op::Increment { amount, .. } => CounterEvent::Incremented {
+                counter,
+                amount,
+                previous,
+                current,
+            },
+            Op::Decrement { amount, .. } => CounterEvent::Decremented {
+                counter,
+                amount,
+                previous,
+                current,
+            },
+            Op::Set { .. } => CounterEvent::ValueSet {
+                counter,
+                previous,
+                current,
+            },
+        });
     }
 
-    counter_data.serialize(&mut *acc.data.borrow_mut())?;
+    for (index, acc) in counters.iter().enumerate() {
+        if !touched[index] {
+            continue;
+        }
+        let mut data = acc.data.borrow_mut();
+        let counter_data = CounterState::load_mut(&mut data)?;
+        counter_data.set_count(counts[index]);
+        counter_data.set_version(counter_data.version().wrapping_add(1));
+    }
+    for event in &events {
+        event.emit()?;
+    }
 
-    msg!("Counter updated to {}", counter_data.count);
+    msg!("Applied {} ops to {} counters", ops.len(), counters.len());
     Ok(())
 }
+
+/// Expands `deltas` into one op per counter and applies them as a batch.
+fn process_update_many(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    deltas: Deltas,
+) -> ProgramResult {
+    let (_, counters, _) = split_batch_accounts(program_id, accounts)?;
+    let counters = counters.len();
+    let deltas = match deltas {
+        Deltas::Same(delta) => vec![delta; counters],
+        Deltas::PerAccount(deltas) => {
+            if deltas.len() != counters {
+                msg!("Expected {} deltas, got {}", counters, deltas.len());
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            deltas
+        }
+    };
+
+    let ops = deltas
+        .into_iter()
+        .enumerate()
+        .map(|(index, delta)| {
+            let counter = u8::try_from(index).map_err(|_| ProgramError::InvalidArgument)?;
+            Ok(match delta {
+                Delta::Increment(amount) => Op::Increment { counter, amount },
+                Delta::Decrement(amount) => Op::Decrement { counter, amount },
+            })
+        })
+        .collect::<Result<Vec<_>, ProgramError>>()?;
+    process_batch(program_id, accounts, &ops)
+}
+
+/// Moves `amount` between two counters of the same kind, checking that their
+/// total is unchanged before writing either of them.
+fn process_transfer(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let source = next_account_info(accounts_iter)?;
+    let destination = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    check_counter_account(program_id, source)?;
+    check_counter_account(program_id, destination)?;
+    if source.key == destination.key {
+        msg!("Cannot transfer from counter {} to itself", source.key);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut source_data = source.data.borrow_mut();
+    check_signer(
+        &source_data,
+        authority,
+        Permission::Value(Role::Decrementer),
+        accounts_iter.as_slice(),
+    )?;
+    let source_counter = CounterState::load_mut(&mut source_data)?;
+    source_counter.check_not_monotonic()?;
+    let mut destination_data = destination.data.borrow_mut();
+    check_signer(
+        &destination_data,
+        authority,
+        Permission::Value(Role::Incrementer),
+        accounts_iter.as_slice(),
+    )?;
+    let destination_counter = CounterState::load_mut(&mut destination_data)?;
+
+    let source_previous = source_counter.count();
+    let destination_previous = destination_counter.count();
+    source_previous.check_kind(destination_previous)?;
+    let source_current = check_update(
+        source_counter,
+        source_previous,
+        source_previous.sub(amount, source_counter.policy())?,
+    )?;
+    let destination_current = check_update(
+        destination_counter,
+        destination_previous,
+        destination_previous.add(amount, destination_counter.policy())?,
+    )?;
+
+    let total = source_previous.to_i128() + destination_previous.to_i128();
+    if source_current.to_i128() + destination_current.to_i128() != total {
+        msg!(
+            "Transfer would change the total from {} to {} + {}",
+            total,
+            source_current,
+            destination_current
+        );
+        return Err(CounterError::TotalNotConserved.into());
+    }
+
+    source_counter.set_count(source_current);
+    source_counter.set_version(source_counter.version().wrapping_add(1));
+    destination_counter.set_count(destination_current);
+    destination_counter.set_version(destination_counter.version().wrapping_add(1));
+
+    CounterEvent::Decremented {
+        counter: *source.key,
+        amount,
+        previous: source_previous,
+        current: source_current,
+    }
+    .emit()?;
+    CounterEvent::Incremented {
+        counter: *destination.key,
+        amount,
+        previous: destination_previous,
+        current: destination_current,
+    }
+    .emit()?;
+    msg!("Transferred {} from {} to {}", amount, source.key, destination.key);
+    Ok(())
+}
+
+/// Hands the counter over to its pending authority, which must sign.
+fn process_accept_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let acc = next_account_info(accounts_iter)?;
+    let pending_authority = next_account_info(accounts_iter)?;
+    check_counter_account(program_id, acc)?;
+
+    let mut data = acc.data.borrow_mut();
+    let counter_data = CounterState::load_mut(&mut data)?;
+    let pending = *counter_data
+        .pending_authority()
+        .ok_or(CounterError::NoPendingAuthority)?;
+    check_authority(&pending, pending_authority, accounts_iter.as_slice())?;
+
+    let previous = *counter_data.authority();
+    counter_data.set_authority(pending);
+    counter_data.set_pending_authority(None);
+    counter_data.set_version(counter_data.version().wrapping_add(1));
+
+    CounterEvent::AuthorityChanged {
+        counter: *acc.key,
+        previous,
+        new: pending,
+    }
+    .emit()?;
+    msg!("Counter {} authority changed from {} to {}", acc.key, previous, pending);
+    revoke_admins(acc.key, &mut data)
+}
+
+/// Adds `role` to `member`'s entry in the role table, appending an entry and
+/// topping up the rent from the payer if the member has none.
+fn process_grant_role(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    member: Pubkey,
+    role: Role,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let acc = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    let payer = next_account_info(accounts_iter)?;
+    let system_program_acc = next_account_info(accounts_iter)?;
+    check_counter_account(program_id, acc)?;
+
+    let (index, grow) = {
+        let data = acc.data.borrow();
+        check_role_manager(&data, authority, role, accounts_iter.as_slice())?;
+        let entries = role_entries(&data);
+        let index = entries
+            .iter()
+            .position(|entry| entry.member == member)
+            .or_else(|| entries.iter().position(RoleEntry::is_empty));
+        match index {
+            Some(index) => (index, false),
+            None if entries.len() < MAX_ROLE_MEMBERS => (entries.len(), true),
+            None => {
+                msg!("Counter {} already has {} role members", acc.key, MAX_ROLE_MEMBERS);
+                return Err(CounterError::RoleTableFull.into());
+            }
+        }
+    };
+
+    if grow {
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if system_program_acc.key != &system_program::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let len = acc.data_len() + RoleEntry::LEN;
+        let top_up = Rent::get()?
+            .minimum_balance(len)
+            .saturating_sub(acc.lamports());
+        if top_up > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, acc.key, top_up),
+                &[payer.clone(), acc.clone(), system_program_acc.clone()],
+            )?;
+        }
+        acc.resize(len)?;
+    }
+
+    let mut data = acc.data.borrow_mut();
+    let entry = &mut role_entries_mut(&mut data)[index];
+    entry.member = member;
+    entry.grant(role);
+    let counter_data = CounterState::load_mut(&mut data)?;
+    counter_data.set_version(counter_data.version().wrapping_add(1));
+
+    CounterEvent::RoleGranted {
+        counter: *acc.key,
+        member,
+        role,
+    }
+    .emit()?;
+    msg!("{:?} role granted to {} on counter {}", role, member, acc.key);
+    Ok(())
+}
+
+/// Removes `role` from `member`. Entries left without roles stay in the table
+/// to be reused by the next grant.
+fn process_revoke_role(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    member: Pubkey,
+    role: Role,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let acc = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    check_counter_account(program_id, acc)?;
+
+    let mut data = acc.data.borrow_mut();
+    check_role_manager(&data, authority, role, accounts_iter.as_slice())?;
+    let Some(entry) = role_entries_mut(&mut data)
+        .iter_mut()
+        .find(|entry| entry.member == member && entry.has(role))
+    else {
+        msg!("{} does not hold the {:?} role", member, role);
+        return Err(CounterError::MissingRole.into());
+    };
+    entry.revoke(role);
+    let counter_data = CounterState::load_mut(&mut data)?;
+    counter_data.set_version(counter_data.version().wrapping_add(1));
+
+    CounterEvent::RoleRevoked {
+        counter: *acc.key,
+        member,
+        role,
+    }
+    .emit()?;
+    msg!("{:?} role revoked from {} on counter {}", role, member, acc.key);
+    Ok(())
+}
+
+/// Points the counter's authority at its multisig, creating the multisig
+/// account on first use and overwriting its signers and threshold otherwise.
+fn process_set_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    signers: &[Pubkey],
+    threshold: u8,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let acc = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    let multisig = next_account_info(accounts_iter)?;
+    let payer = next_account_info(accounts_iter)?;
+    let system_program_acc = next_account_info(accounts_iter)?;
+    check_counter_account(program_id, acc)?;
+
+    let previous = {
+        let data = acc.data.borrow();
+        check_signer(&data, authority, Permission::Authority, accounts_iter.as_slice())?;
+        *CounterState::load(&data)?.authority()
+    };
+
+    let (expected, bump) = find_multisig_address(acc.key);
+    if multisig.key != &expected {
+        msg!("Expected multisig address {}, got {}", expected, multisig.key);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let multisig_data = Multisig::new(bump, signers, threshold)?;
+    if let Some(signer) = signers
+        .iter()
+        .find(|signer| [Pubkey::default(), *acc.key, *multisig.key].contains(signer))
+    {
+        msg!("{} cannot sign for a multisig", signer);
+        return Err(CounterError::InvalidMultisig.into());
+    }
+    // The new signers prove they hold their keys, so a mistyped signer
+    // cannot lock the counter behind a multisig nobody can approve for.
+    check_signatures(multisig.key, signers, threshold, accounts_iter.as_slice())?;
+    if multisig.owner != program_id {
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if system_program_acc.key != &system_program::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let signer_seeds: &[&[u8]] = &[MULTISIG_SEED, acc.key.as_ref(), &[bump]];
+        create_pda_account(
+            payer,
+            multisig,
+            system_program_acc,
+            program_id,
+            Multisig::LEN,
+            signer_seeds,
+        )?;
+    }
+    multisig
+        .data
+        .borrow_mut()
+        .copy_from_slice(bytemuck::bytes_of(&multisig_data));
+
+    let mut data = acc.data.borrow_mut();
+    let counter_data = CounterState::load_mut(&mut data)?;
+    counter_data.set_authority(*multisig.key);
+    counter_data.set_pending_authority(None);
+    counter_data.set_version(counter_data.version().wrapping_add(1));
+
+    if previous != *multisig.key {
+        CounterEvent::AuthorityChanged {
+            counter: *acc.key,
+            previous,
+            new: *multisig.key,
+        }
+        .emit()?;
+        revoke_admins(acc.key, &mut data)?;
+    }
+    CounterEvent::MultisigSet {
+        counter: *acc.key,
+        multisig: *multisig.key,
+        signers: signers.to_vec(),
+        threshold,
+    }
+    .emit()?;
+    msg!(
+        "Counter {} now requires {} of {} signers of multisig {}",
+        acc.key,
+        threshold,
+        signers.len(),
+        multisig.key
+    );
+    Ok(())
+}
+
+/// Requires a signer allowed to grant and revoke `role`: the authority for
+/// `Admin`, and the authority or an admin for the other roles.
+fn check_role_manager(
+    data: &[u8],
+    signer: &AccountInfo,
+    role: Role,
+    cosigners: &[AccountInfo],
+) -> ProgramResult {
+    let required = if role == Role::Admin {
+        Permission::Authority
+    } else {
+        Permission::Admin
+    };
+    check_signer(data, signer, required, cosigners)
+}
+
+/// Revokes the admin role from every member when the authority changes
+/// hands, so admins the outgoing authority granted, possibly to itself, do
+/// not keep control of the settings behind the new authority's back.
+fn revoke_admins(counter: &Pubkey, data: &mut [u8]) -> ProgramResult {
+    let mut revoked = Vec::new();
+    for entry in role_entries_mut(data) {
+        if entry.has(Role::Admin) {
+            entry.revoke(Role::Admin);
+            revoked.push(entry.member);
+        }
+    }
+    for member in revoked {
+        CounterEvent::RoleRevoked {
+            counter: *counter,
+            member,
+            role: Role::Admin,
+        }
+        .emit()?;
+        msg!("Admin role revoked from {} on counter {}", member, counter);
+    }
+    Ok(())
+}
+
+/// Applies the counter's bounds to `current` and rejects decreases of
+/// monotonic counters, returning the value to store.
+fn check_update(
+    counter_data: &CounterState,
+    previous: CounterValue,
+    current: CounterValue,
+) -> Result<CounterValue, ProgramError> {
+    let current = counter_data.bounds().apply(current)?;
+    if counter_data.monotonic() && current.to_i128() < previous.to_i128() {
+        msg!("Monotonic counter cannot go from {} to {}", previous, current);
+        return Err(CounterError::MonotonicViolation.into());
+    }
+    Ok(current)
+}
+
+/// Rejects counter accounts this program cannot safely write to.
+fn check_counter_account(program_id: &Pubkey, acc: &AccountInfo) -> ProgramResult {
+    check_counter_owner(program_id, acc)?;
+    if !acc.is_writable {
+        msg!("Counter account {} is not writable", acc.key);
+        return Err(ProgramError::Immutable);
+    }
+    Ok(())
+}
+
+/// Rejects counter accounts this program cannot trust the contents of.
+/// Executable accounts are rejected first: they are owned by a loader, so the
+/// owner check would otherwise hide them.
+fn check_counter_owner(program_id: &Pubkey, acc: &AccountInfo) -> ProgramResult {
+    if acc.executable {
+        msg!("Counter account {} is executable", acc.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if acc.owner != program_id {
+        msg!("Counter account {} is not owned by this program", acc.key);
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Requires a signature with `permission` over the counter in `data`: its
+/// authority's (or its multisig signers' among `cosigners`), a holder of the
+/// role `permission` names, or any signer's for value changes once the
+/// authority was renounced in `Open` mode. Only `Permission::Value` survives
+/// renouncing, so handlers cannot forget to protect the settings.
+fn check_signer(
+    data: &[u8],
+    signer: &AccountInfo,
+    permission: Permission,
+    cosigners: &[AccountInfo],
+) -> ProgramResult {
+    let counter_data = CounterState::load(data)?;
+    counter_data.check_mutable()?;
+    if !matches!(permission, Permission::Value(_)) {
+        counter_data.require_authority()?;
+    }
+    if signer.key == counter_data.authority() {
+        return check_authority(counter_data.authority(), signer, cosigners);
+    }
+    if !signer.is_signer {
+        msg!("Signer {} did not sign", signer.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let role = match permission {
+        Permission::Value(_) if counter_data.authority_mode() == AuthorityMode::Open => {
+            return Ok(());
+        }
+        Permission::Value(role) => role,
+        Permission::Admin => Role::Admin,
+        Permission::Authority => {
+            msg!("Expected authority {}, got {}", counter_data.authority(), signer.key);
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+    };
+    if !has_role(data, signer.key, role) {
+        msg!("{} does not hold the {:?} role", signer.key, role);
+        return Err(CounterError::MissingRole.into());
+    }
+    Ok(())
+}
+
+/// Role a batch signer needs for `op`.
+fn op_role(op: &Op) -> Role {
+    match op {
+        Op::Increment { .. } => Role::Incrementer,
+        Op::Decrement { .. } => Role::Decrementer,
+        Op::Set { .. } => Role::Admin,
+    }
+}
+
+/// Requires the counter's authority to have signed or, if the authority is a
+/// multisig, enough of its signers among `cosigners`. A counter without an
+/// authority is never handed to whoever signs first: it has no authority to
+/// sign for.
+fn check_authority(
+    expected: &Pubkey,
+    authority: &AccountInfo,
+    cosigners: &[AccountInfo],
+) -> ProgramResult {
+    if *expected == Pubkey::default() {
+        msg!("Counter has no authority to sign for it");
+        return Err(CounterError::NoAuthority.into());
+    }
+    if expected != authority.key {
+        msg!("Expected authority {}, got {}", expected, authority.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if authority.is_signer {
+        return Ok(());
+    }
+    if authority.owner == &ID {
+        return check_multisig(authority, cosigners);
+    }
+    msg!("Authority {} did not sign", authority.key);
+    Err(ProgramError::MissingRequiredSignature)
+}
+
+/// Requires enough of the signers stored in `multisig` among `cosigners`.
+fn check_multisig(multisig: &AccountInfo, cosigners: &[AccountInfo]) -> ProgramResult {
+    let data = multisig.data.borrow();
+    let multisig_data = Multisig::load(&data)?;
+    check_signatures(
+        multisig.key,
+        multisig_data.signers(),
+        multisig_data.threshold(),
+        cosigners,
+    )
+}
+
+/// Requires `threshold` of `signers` of `multisig` to have signed among
+/// `cosigners`, logging each signer whose signature is missing.
+fn check_signatures(
+    multisig: &Pubkey,
+    signers: &[Pubkey],
+    threshold: u8,
+    cosigners: &[AccountInfo],
+) -> ProgramResult {
+    let missing: Vec<&Pubkey> = signers
+        .iter()
+        .filter(|signer| !cosigners.iter().any(|acc| acc.is_signer && acc.key == *signer))
+        .collect();
+    let signed = signers.len() - missing.len();
+    if signed < usize::from(threshold) {
+        msg!(
+            "Multisig {} has {} of the {} signatures it requires",
+            multisig,
+            signed,
+            threshold
+        );
+        for signer in missing {
+            msg!("Missing signature from {}", signer);
+        }
+        return Err(CounterError::NotEnoughSigners.into());
+    }
+    Ok(())
+}
+
+/// Creates a rent-exempt account owned by `owner` at a PDA. Handles PDAs that
+/// were pre-funded by a third party, where `create_account` would fail.
+fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    acc: &AccountInfo<'a>,
+    system_program_acc: &AccountInfo<'a>,
+    owner: &Pubkey,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let required_lamports = Rent::get()?.minimum_balance(space);
+
+    if acc.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                acc.key,
+                required_lamports,
+                space as u64,
+                owner,
+            ),
+            &[payer.clone(), acc.clone(), system_program_acc.clone()],
+            &[signer_seeds],
+        );
+    }
+
+    let top_up = required_lamports.saturating_sub(acc.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, acc.key, top_up),
+            &[payer.clone(), acc.clone(), system_program_acc.clone()],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(acc.key, space as u64),
+        &[acc.clone(), system_program_acc.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(acc.key, owner),
+        &[acc.clone(), system_program_acc.clone()],
+        &[signer_seeds],
+    )
+}
@@ -0,0 +1,769 @@
+//! Layout of counter accounts and the derivation of their addresses.
+
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_program::{
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::{Pubkey, PubkeyError},
+};
+
+use crate::{ID, error::CounterError};
+
+pub const COUNTER_SEED: &[u8] = b"counter";
+pub const COUNTER_DISCRIMINATOR: [u8; 8] = *b"counter\0";
+pub const LAYOUT_VERSION: u8 = 2;
+pub const MULTISIG_SEED: &[u8] = b"multisig";
+pub const MULTISIG_DISCRIMINATOR: [u8; 8] = *b"multisig";
+/// Size of the original `Counter { count: u32 }` accounts.
+pub(crate) const LEGACY_LEN: usize = 4;
+/// Fields added without a layout version bump are carved out of the reserved
+/// space, so `ACCOUNT_LEN` stays fixed and their zeroed bytes must decode as
+/// the field's default.
+pub(crate) const RESERVED_LEN: usize = 74;
+/// Size of every counter account on the current layout, before its role
+/// table.
+pub const ACCOUNT_LEN: usize = AccountHeader::LEN + Counter::LEN + RESERVED_LEN;
+
+/// Prefix of every counter account, followed by the `Counter` body and
+/// `RESERVED_LEN` zeroed bytes kept free for future fields.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct AccountHeader {
+    discriminator: [u8; 8],
+    pub(crate) layout_version: u8,
+    pub(crate) bump: u8
+}
+
+impl AccountHeader {
+    pub(crate) const LEN: usize = 8 + 1 + 1;
+
+    pub(crate) fn new(bump: u8) -> Self {
+        Self {
+            discriminator: COUNTER_DISCRIMINATOR,
+            layout_version: LAYOUT_VERSION,
+            bump,
+        }
+    }
+
+    /// Reads the header of any counter layout, leaving `data` at the body.
+    pub(crate) fn load(data: &mut &[u8]) -> Result<Self, ProgramError> {
+        let header = Self::deserialize(data)?;
+        if header.discriminator != COUNTER_DISCRIMINATOR {
+            if header.discriminator == [0; 8] {
+                return Err(ProgramError::UninitializedAccount);
+            }
+            return Err(CounterError::InvalidAccountDiscriminator.into());
+        }
+        Ok(header)
+    }
+
+    pub(crate) fn store(&self, data: &mut [u8]) -> ProgramResult {
+        self.serialize(&mut &mut data[..])?;
+        Ok(())
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterKind {
+    Unsigned,
+    Signed
+}
+
+/// Who may update the counter.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AuthorityMode {
+    /// Only the counter's authority.
+    #[default]
+    Authority,
+    /// Any signer may change the value, and nobody may change the settings
+    /// or close the counter.
+    Open,
+    /// Nobody may change or close the counter.
+    Immutable
+}
+
+/// Permission the authority can grant to other accounts. The authority itself
+/// holds every role.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// May send every instruction the other roles may, plus `Reset`, the set
+    /// instructions, `SetBounds` and grants of the other roles.
+    /// Revoked from every member when the authority changes.
+    Admin,
+    Incrementer,
+    Decrementer
+}
+
+impl Role {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// What `Increment` and `Decrement` do when the result leaves the counter's range.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Fail with `CounterError::Overflow` or `CounterError::Underflow`.
+    #[default]
+    Checked,
+    /// Clamp to the counter's minimum or maximum.
+    Saturating,
+    /// Wrap around modulo the counter's width.
+    Wrapping
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterValue {
+    Unsigned(u64),
+    Signed(i64)
+}
+
+impl CounterValue {
+    pub fn zero(kind: CounterKind) -> Self {
+        match kind {
+            CounterKind::Unsigned => Self::Unsigned(0),
+            CounterKind::Signed => Self::Signed(0),
+        }
+    }
+
+    pub fn kind(self) -> CounterKind {
+        match self {
+            Self::Unsigned(_) => CounterKind::Unsigned,
+            Self::Signed(_) => CounterKind::Signed,
+        }
+    }
+
+    pub fn add(self, amount: u64, policy: OverflowPolicy) -> Result<Self, CounterError> {
+        match (self, policy) {
+            (Self::Unsigned(v), OverflowPolicy::Checked) => {
+                v.checked_add(amount).map(Self::Unsigned)
+            }
+            (Self::Signed(v), OverflowPolicy::Checked) => {
+                v.checked_add_unsigned(amount).map(Self::Signed)
+            }
+            (Self::Unsigned(v), OverflowPolicy::Saturating) => {
+                Some(Self::Unsigned(v.saturating_add(amount)))
+            }
+            (Self::Signed(v), OverflowPolicy::Saturating) => {
+                Some(Self::Signed(v.saturating_add_unsigned(amount)))
+            }
+            (Self::Unsigned(v), OverflowPolicy::Wrapping) => {
+                Some(Self::Unsigned(v.wrapping_add(amount)))
+            }
+            (Self::Signed(v), OverflowPolicy::Wrapping) => {
+                Some(Self::Signed(v.wrapping_add_unsigned(amount)))
+            }
+        }
+        .ok_or(CounterError::Overflow)
+    }
+
+    pub fn sub(self, amount: u64, policy: OverflowPolicy) -> Result<Self, CounterError> {
+        match (self, policy) {
+            (Self::Unsigned(v), OverflowPolicy::Checked) => {
+                v.checked_sub(amount).map(Self::Unsigned)
+            }
+            (Self::Signed(v), OverflowPolicy::Checked) => {
+                v.checked_sub_unsigned(amount).map(Self::Signed)
+            }
+            (Self::Unsigned(v), OverflowPolicy::Saturating) => {
+                Some(Self::Unsigned(v.saturating_sub(amount)))
+            }
+            (Self::Signed(v), OverflowPolicy::Saturating) => {
+                Some(Self::Signed(v.saturating_sub_unsigned(amount)))
+            }
+            (Self::Unsigned(v), OverflowPolicy::Wrapping) => {
+                Some(Self::Unsigned(v.wrapping_sub(amount)))
+            }
+            (Self::Signed(v), OverflowPolicy::Wrapping) => {
+                Some(Self::Signed(v.wrapping_sub_unsigned(amount)))
+            }
+        }
+        .ok_or(CounterError::Underflow)
+    }
+
+    pub(crate) fn to_i128(self) -> i128 {
+        match self {
+            Self::Unsigned(v) => v.into(),
+            Self::Signed(v) => v.into(),
+        }
+    }
+
+    /// Rejects values of a different kind than `self`.
+    pub(crate) fn check_kind(self, other: Self) -> Result<(), CounterError> {
+        if self.kind() != other.kind() {
+            msg!("Expected a {:?} value, got {:?}", self.kind(), other.kind());
+            return Err(CounterError::KindMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// What happens to updates that leave `[min, max]`.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoundsMode {
+    #[default]
+    Unbounded,
+    /// Fail with `CounterError::OutOfBounds`.
+    Reject,
+    /// Clamp the result into `[min, max]`.
+    Clamp
+}
+
+/// Inclusive range the counter must stay in, checked after every update.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub mode: BoundsMode,
+    pub min: CounterValue,
+    pub max: CounterValue
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self {
+            mode: BoundsMode::Unbounded,
+            min: CounterValue::Unsigned(0),
+            max: CounterValue::Unsigned(0),
+        }
+    }
+}
+
+impl Bounds {
+    pub(crate) fn validate(&self, kind: CounterKind) -> Result<(), CounterError> {
+        if self.mode == BoundsMode::Unbounded {
+            return Ok(());
+        }
+        if self.min.kind() != kind || self.max.kind() != kind {
+            return Err(CounterError::KindMismatch);
+        }
+        if self.min.to_i128() > self.max.to_i128() {
+            return Err(CounterError::InvalidBounds);
+        }
+        Ok(())
+    }
+
+    /// Returns `value` if it is in range, or what the mode makes of it otherwise.
+    pub(crate) fn apply(&self, value: CounterValue) -> Result<CounterValue, CounterError> {
+        let (min, max) = (self.min.to_i128(), self.max.to_i128());
+        let raw = value.to_i128();
+        if self.mode == BoundsMode::Unbounded || (min..=max).contains(&raw) {
+            return Ok(value);
+        }
+        msg!("Value {} is outside [{}, {}]", value, self.min, self.max);
+        match self.mode {
+            BoundsMode::Reject => Err(CounterError::OutOfBounds),
+            _ if raw < min => Ok(self.min),
+            _ => Ok(self.max),
+        }
+    }
+}
+
+impl fmt::Display for CounterValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unsigned(v) => v.fmt(f),
+            Self::Signed(v) => v.fmt(f),
+        }
+    }
+}
+
+/// Body of a counter account, following its `AccountHeader`.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Eq)]
+pub struct Counter {
+    pub count: CounterValue,
+    pub authority: Pubkey,
+    /// Bumped on every write, for optimistic concurrency.
+    pub version: u64,
+    pub policy: OverflowPolicy,
+    pub bounds: Bounds,
+    pub monotonic: bool,
+    /// Proposed by `ProposeAuthority` and waiting to be accepted, or the
+    /// default pubkey if no transfer is in progress.
+    pub pending_authority: Pubkey,
+    /// Set by `RenounceAuthority`, which also clears `authority`.
+    pub authority_mode: AuthorityMode
+}
+
+impl Counter {
+    pub const LEN: usize = 9 + 32 + 8 + 1 + 19 + 1 + 32 + 1;
+
+    /// Reads the counter body, rejecting accounts that are not counters or use
+    /// an older layout. Trailing reserved bytes are ignored.
+    pub fn load(data: &[u8]) -> Result<Self, ProgramError> {
+        let mut data = data;
+        let header = AccountHeader::load(&mut data)?;
+        if header.layout_version != LAYOUT_VERSION {
+            msg!("Counter uses layout version {}, migrate it first", header.layout_version);
+            return Err(CounterError::UnsupportedLayoutVersion.into());
+        }
+        Ok(Self::deserialize(&mut data)?)
+    }
+
+    /// Writes the counter body after the header, leaving the header untouched.
+    pub fn store(&self, data: &mut [u8]) -> ProgramResult {
+        self.serialize(&mut &mut data[AccountHeader::LEN..])?;
+        Ok(())
+    }
+}
+
+/// Zero-copy view of a counter account on the current layout, read and
+/// written in place instead of round-tripping the whole `Counter` through
+/// borsh. It mirrors the borsh encoding of `AccountHeader` followed by
+/// `Counter` byte for byte: integers are little-endian byte arrays, so the
+/// struct has an alignment of 1 and no padding.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct CounterState {
+    discriminator: [u8; 8],
+    layout_version: u8,
+    bump: u8,
+    count: PodCounterValue,
+    authority: Pubkey,
+    version: [u8; 8],
+    policy: u8,
+    bounds: PodBounds,
+    monotonic: u8,
+    pending_authority: Pubkey,
+    authority_mode: u8
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PodCounterValue {
+    kind: u8,
+    value: [u8; 8]
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PodBounds {
+    mode: u8,
+    min: PodCounterValue,
+    max: PodCounterValue
+}
+
+const _: () = assert!(CounterState::LEN == AccountHeader::LEN + Counter::LEN);
+
+impl CounterState {
+    pub const LEN: usize = size_of::<Self>();
+
+    /// Borrows the counter in `data`, with the same checks as `Counter::load`.
+    /// Enum tags are validated here so the accessors cannot fail.
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        let state: &Self = bytemuck::from_bytes(data.get(..Self::LEN).ok_or_else(|| {
+            msg!("Counter account holds {} bytes, expected {}", data.len(), Self::LEN);
+            ProgramError::InvalidAccountData
+        })?);
+        state.validate()?;
+        Ok(state)
+    }
+
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        Self::load(data)?;
+        Ok(bytemuck::from_bytes_mut(&mut data[..Self::LEN]))
+    }
+
+    fn validate(&self) -> ProgramResult {
+        if self.discriminator != COUNTER_DISCRIMINATOR {
+            if self.discriminator == [0; 8] {
+                return Err(ProgramError::UninitializedAccount);
+            }
+            return Err(CounterError::InvalidAccountDiscriminator.into());
+        }
+        if self.layout_version != LAYOUT_VERSION {
+            msg!("Counter uses layout version {}, migrate it first", self.layout_version);
+            return Err(CounterError::UnsupportedLayoutVersion.into());
+        }
+        let kinds = [self.count.kind, self.bounds.min.kind, self.bounds.max.kind];
+        if kinds.iter().any(|&kind| kind > 1)
+            || self.policy > 2
+            || self.bounds.mode > 2
+            || self.monotonic > 1
+            || self.authority_mode > 2
+        {
+            msg!("Counter account holds an invalid enum tag");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+
+    pub fn bump(&self) -> u8 {
+        self.bump
+    }
+
+    pub fn count(&self) -> CounterValue {
+        self.count.get()
+    }
+
+    pub fn set_count(&mut self, count: CounterValue) {
+        self.count = PodCounterValue::new(count);
+    }
+
+    pub fn authority(&self) -> &Pubkey {
+        &self.authority
+    }
+
+    pub fn set_authority(&mut self, authority: Pubkey) {
+        self.authority = authority;
+    }
+
+    /// Returns the proposed authority, if an authority transfer is pending.
+    pub fn pending_authority(&self) -> Option<&Pubkey> {
+        Some(&self.pending_authority).filter(|pending| **pending != Pubkey::default())
+    }
+
+    pub fn set_pending_authority(&mut self, pending_authority: Option<Pubkey>) {
+        self.pending_authority = pending_authority.unwrap_or_default();
+    }
+
+    pub fn authority_mode(&self) -> AuthorityMode {
+        match self.authority_mode {
+            0 => AuthorityMode::Authority,
+            1 => AuthorityMode::Open,
+            _ => AuthorityMode::Immutable,
+        }
+    }
+
+    pub fn set_authority_mode(&mut self, authority_mode: AuthorityMode) {
+        self.authority_mode = authority_mode as u8;
+    }
+
+    /// Rejects changes to counters whose authority was renounced, for
+    /// instructions only the authority may send.
+    pub(crate) fn require_authority(&self) -> Result<(), CounterError> {
+        match self.authority_mode() {
+            AuthorityMode::Authority => Ok(()),
+            AuthorityMode::Open => Err(CounterError::NoAuthority),
+            AuthorityMode::Immutable => Err(CounterError::ImmutableCounter),
+        }
+    }
+
+    /// Rejects any change to immutable counters.
+    pub(crate) fn check_mutable(&self) -> Result<(), CounterError> {
+        if self.authority_mode() == AuthorityMode::Immutable {
+            return Err(CounterError::ImmutableCounter);
+        }
+        Ok(())
+    }
+
+    pub fn version(&self) -> u64 {
+        u64::from_le_bytes(self.version)
+    }
+
+    pub fn set_version(&mut self, version: u64) {
+        self.version = version.to_le_bytes();
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        match self.policy {
+            0 => OverflowPolicy::Checked,
+            1 => OverflowPolicy::Saturating,
+            _ => OverflowPolicy::Wrapping,
+        }
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        Bounds {
+            mode: match self.bounds.mode {
+                0 => BoundsMode::Unbounded,
+                1 => BoundsMode::Reject,
+                _ => BoundsMode::Clamp,
+            },
+            min: self.bounds.min.get(),
+            max: self.bounds.max.get(),
+        }
+    }
+
+    pub fn set_bounds(&mut self, bounds: Bounds) {
+        self.bounds = PodBounds {
+            mode: bounds.mode as u8,
+            min: PodCounterValue::new(bounds.min),
+            max: PodCounterValue::new(bounds.max),
+        };
+    }
+
+    pub fn monotonic(&self) -> bool {
+        self.monotonic != 0
+    }
+
+    /// Rejects instructions that only make sense on counters that can go down.
+    pub(crate) fn check_not_monotonic(&self) -> Result<(), CounterError> {
+        if self.monotonic() {
+            return Err(CounterError::MonotonicViolation);
+        }
+        Ok(())
+    }
+}
+
+impl PodCounterValue {
+    fn new(value: CounterValue) -> Self {
+        match value {
+            CounterValue::Unsigned(v) => Self {
+                kind: CounterKind::Unsigned as u8,
+                value: v.to_le_bytes(),
+            },
+            CounterValue::Signed(v) => Self {
+                kind: CounterKind::Signed as u8,
+                value: v.to_le_bytes(),
+            },
+        }
+    }
+
+    fn get(self) -> CounterValue {
+        match self.kind {
+            0 => CounterValue::Unsigned(u64::from_le_bytes(self.value)),
+            _ => CounterValue::Signed(i64::from_le_bytes(self.value)),
+        }
+    }
+}
+
+/// Return data set by every instruction that updates the counter, and by
+/// `Get`: the borsh encoding of this struct. `Get` reports the current value
+/// as both `previous` and `current`.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterReturnData {
+    pub previous: CounterValue,
+    pub current: CounterValue,
+    pub version: u64
+}
+
+/// Entry of the role table that follows the reserved bytes of a counter
+/// account. The table grows by one entry for each new member, and entries
+/// left without roles are reused.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct RoleEntry {
+    pub member: Pubkey,
+    roles: u8
+}
+
+impl RoleEntry {
+    pub const LEN: usize = size_of::<Self>();
+
+    pub fn has(&self, role: Role) -> bool {
+        self.roles & role.bit() != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roles == 0
+    }
+
+    pub(crate) fn grant(&mut self, role: Role) {
+        self.roles |= role.bit();
+    }
+
+    pub(crate) fn revoke(&mut self, role: Role) {
+        self.roles &= !role.bit();
+    }
+}
+
+/// Most members a counter's role table can hold.
+pub const MAX_ROLE_MEMBERS: usize = 16;
+
+/// Returns the role table of the counter account in `data`.
+pub fn role_entries(data: &[u8]) -> &[RoleEntry] {
+    let table = data.get(ACCOUNT_LEN..).unwrap_or_default();
+    bytemuck::cast_slice(&table[..table.len() / RoleEntry::LEN * RoleEntry::LEN])
+}
+
+pub(crate) fn role_entries_mut(data: &mut [u8]) -> &mut [RoleEntry] {
+    let table = data.get_mut(ACCOUNT_LEN..).unwrap_or_default();
+    let len = table.len() / RoleEntry::LEN * RoleEntry::LEN;
+    bytemuck::cast_slice_mut(&mut table[..len])
+}
+
+/// Whether `member` holds `role` in the counter account in `data`, directly
+/// or through the admin role.
+pub fn has_role(data: &[u8], member: &Pubkey, role: Role) -> bool {
+    role_entries(data)
+        .iter()
+        .any(|entry| entry.member == *member && (entry.has(role) || entry.has(Role::Admin)))
+}
+
+/// Most signers a multisig authority can have.
+pub const MAX_MULTISIG_SIGNERS: usize = 11;
+
+/// M-of-N authority stored at `find_multisig_address(counter)`. A counter
+/// whose authority is a multisig is approved by `threshold` of its signers
+/// instead of by a signature of the authority itself.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Multisig {
+    discriminator: [u8; 8],
+    bump: u8,
+    threshold: u8,
+    signer_count: u8,
+    signers: [Pubkey; MAX_MULTISIG_SIGNERS]
+}
+
+impl Multisig {
+    pub const LEN: usize = size_of::<Self>();
+
+    /// Builds a multisig, rejecting duplicate signers and thresholds that are
+    /// zero or exceed the number of signers.
+    pub fn new(bump: u8, signers: &[Pubkey], threshold: u8) -> Result<Self, CounterError> {
+        if signers.is_empty() || signers.len() > MAX_MULTISIG_SIGNERS {
+            msg!("Multisig needs 1 to {} signers, got {}", MAX_MULTISIG_SIGNERS, signers.len());
+            return Err(CounterError::InvalidMultisig);
+        }
+        if threshold == 0 || usize::from(threshold) > signers.len() {
+            msg!("Threshold {} is not between 1 and {}", threshold, signers.len());
+            return Err(CounterError::InvalidMultisig);
+        }
+        if let Some(signer) = signers
+            .iter()
+            .enumerate()
+            .find_map(|(i, signer)| signers[..i].contains(signer).then_some(signer))
+        {
+            msg!("Signer {} is listed more than once", signer);
+            return Err(CounterError::InvalidMultisig);
+        }
+
+        let mut multisig = Self::zeroed();
+        multisig.discriminator = MULTISIG_DISCRIMINATOR;
+        multisig.bump = bump;
+        multisig.threshold = threshold;
+        multisig.signer_count = signers.len() as u8;
+        multisig.signers[..signers.len()].copy_from_slice(signers);
+        Ok(multisig)
+    }
+
+    /// Borrows the multisig in `data`, rejecting accounts that are not one.
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        let multisig: &Self = bytemuck::try_from_bytes(data).map_err(|_| {
+            msg!("Multisig account holds {} bytes, expected {}", data.len(), Self::LEN);
+            ProgramError::InvalidAccountData
+        })?;
+        if multisig.discriminator != MULTISIG_DISCRIMINATOR
+            || usize::from(multisig.signer_count) > MAX_MULTISIG_SIGNERS
+        {
+            msg!("Account is not a multisig");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(multisig)
+    }
+
+    pub fn bump(&self) -> u8 {
+        self.bump
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    pub fn signers(&self) -> &[Pubkey] {
+        &self.signers[..usize::from(self.signer_count)]
+    }
+}
+
+/// Body of layout version 1, before counters were widened to 64 bits.
+#[derive(BorshDeserialize)]
+pub(crate) struct CounterV1 {
+    pub(crate) count: u32,
+    pub(crate) authority: Pubkey,
+    pub(crate) version: u64
+}
+
+/// Returns the counter PDA that `payer` initializes with `seed`, and its bump.
+pub fn find_counter_address(payer: &Pubkey, seed: &[u8]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COUNTER_SEED, payer.as_ref(), seed], &ID)
+}
+
+/// Returns the address of the multisig that can become `counter`'s authority,
+/// and its bump.
+pub fn find_multisig_address(counter: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MULTISIG_SEED, counter.as_ref()], &ID)
+}
+
+/// Recomputes a counter PDA from a known bump, which is cheaper than
+/// `find_counter_address` on-chain.
+pub fn create_counter_address(
+    payer: &Pubkey,
+    seed: &[u8],
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[COUNTER_SEED, payer.as_ref(), seed, &[bump]], &ID)
+}
+
+/// Recomputes a multisig PDA from the bump stored in it, which is cheaper than
+/// `find_multisig_address` on-chain.
+pub fn create_multisig_address(counter: &Pubkey, bump: u8) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[MULTISIG_SEED, counter.as_ref(), &[bump]], &ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stores `counter` after a current header, as `Initialize` does.
+    fn stored(counter: &Counter) -> Vec<u8> {
+        let mut data = vec![0; ACCOUNT_LEN];
+        AccountHeader::new(254).store(&mut data).unwrap();
+        counter.store(&mut data).unwrap();
+        data
+    }
+
+    /// A counter with a non-default value in every field.
+    fn counter() -> Counter {
+        Counter {
+            count: CounterValue::Signed(-5),
+            authority: Pubkey::new_unique(),
+            version: 9,
+            policy: OverflowPolicy::Wrapping,
+            bounds: Bounds {
+                mode: BoundsMode::Clamp,
+                min: CounterValue::Signed(-10),
+                max: CounterValue::Signed(10),
+            },
+            monotonic: true,
+            pending_authority: Pubkey::new_unique(),
+            authority_mode: AuthorityMode::Immutable,
+        }
+    }
+
+    #[test]
+    fn pod_view_reads_what_borsh_stores() {
+        let counter = counter();
+        let data = stored(&counter);
+
+        let state = CounterState::load(&data).unwrap();
+        assert_eq!(state.bump(), 254);
+        assert_eq!(state.count(), counter.count);
+        assert_eq!(state.authority(), &counter.authority);
+        assert_eq!(state.version(), counter.version);
+        assert_eq!(state.policy(), counter.policy);
+        assert_eq!(state.bounds(), counter.bounds);
+        assert_eq!(state.monotonic(), counter.monotonic);
+        assert_eq!(state.pending_authority(), Some(&counter.pending_authority));
+        assert_eq!(state.authority_mode(), counter.authority_mode);
+    }
+
+    #[test]
+    fn borsh_reads_what_the_pod_view_writes() {
+        let mut data = stored(&counter());
+        let expected = Counter {
+            count: CounterValue::Unsigned(u64::MAX),
+            authority: Pubkey::new_unique(),
+            version: u64::MAX - 1,
+            bounds: Bounds {
+                mode: BoundsMode::Reject,
+                min: CounterValue::Unsigned(1),
+                max: CounterValue::Unsigned(u64::MAX),
+            },
+            pending_authority: Pubkey::default(),
+            authority_mode: AuthorityMode::Open,
+            ..counter()
+        };
+
+        let state = CounterState::load_mut(&mut data).unwrap();
+        state.set_count(expected.count);
+        state.set_authority(expected.authority);
+        state.set_version(expected.version);
+        state.set_bounds(expected.bounds);
+        state.set_pending_authority(None);
+        state.set_authority_mode(expected.authority_mode);
+        assert_eq!(Counter::load(&data).unwrap(), expected);
+    }
+}
@@ -0,0 +1,111 @@
+import * as borsh from "borsh";
+import {
+    Connection,
+    Keypair,
+    PublicKey,
+    SystemProgram,
+    Transaction,
+    TransactionInstruction,
+    sendAndConfirmTransaction
+} from "@solana/web3.js";
+import { COUNTER_SEED, instructionSchema, type CounterInstruction } from "./types";
+
+// Prints the compute units each counter instruction consumes. If
+// BASELINE_RPC_URL points at a second validator running another build of the
+// program, such as the borsh state path before zero-copy accounts, the two
+// builds are compared side by side instead.
+
+const PROGRAM_ID = new PublicKey("CC6Jc1wkfdyyiRGQAGy8UVXXZdb9LDRbc7hJnrxdC44U");
+const RPC_URL = process.env.SOLANA_RPC_URL || "http://127.0.0.1:8899";
+const BASELINE_RPC_URL = process.env.BASELINE_RPC_URL;
+
+if (!process.env.SOLANA_PRIVATE_KEY) {
+    throw new Error("SOLANA_PRIVATE_KEY environment variable is required");
+}
+
+const payer = Keypair.fromSecretKey(new Uint8Array(JSON.parse(process.env.SOLANA_PRIVATE_KEY)));
+
+function serializeInstruction(instruction: CounterInstruction): Buffer {
+    return Buffer.from(borsh.serialize(instructionSchema, instruction));
+}
+
+/**
+ * Simulates a counter instruction signed by the payer
+ * @returns The compute units consumed by the transaction
+ */
+async function computeUnits(
+    connection: Connection,
+    instruction: CounterInstruction,
+    keys: TransactionInstruction["keys"]
+): Promise<number> {
+    const ix = new TransactionInstruction({
+        keys,
+        programId: PROGRAM_ID,
+        data: serializeInstruction(instruction),
+    });
+    const { value } = await connection.simulateTransaction(new Transaction().add(ix), [payer]);
+    if (value.err || value.unitsConsumed === undefined) {
+        throw new Error(`Simulation of ${JSON.stringify(instruction)} failed: ${JSON.stringify(value.err)}`);
+    }
+    return value.unitsConsumed;
+}
+
+/**
+ * Initializes a fresh counter and simulates each benchmarked instruction on it
+ * @param rpcUrl - Validator running the build to measure
+ * @returns The compute units consumed, by instruction name
+ */
+async function measure(rpcUrl: string): Promise<Record<string, number>> {
+    const connection = new Connection(rpcUrl, "confirmed");
+    const seed = Keypair.generate().publicKey.toBytes().slice(0, 8);
+    const [counter] = PublicKey.findProgramAddressSync(
+        [COUNTER_SEED, payer.publicKey.toBuffer(), seed],
+        PROGRAM_ID
+    );
+
+    const initialize = new TransactionInstruction({
+        keys: [
+            { pubkey: payer.publicKey, isSigner: true, isWritable: true },
+            { pubkey: counter, isSigner: false, isWritable: true },
+            { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction({
+            Initialize: { seed, kind: { Unsigned: {} }, policy: { Checked: {} }, monotonic: false },
+        }),
+    });
+    await sendAndConfirmTransaction(connection, new Transaction().add(initialize), [payer]);
+
+    const updateKeys = [
+        { pubkey: counter, isSigner: false, isWritable: true },
+        { pubkey: payer.publicKey, isSigner: true, isWritable: false },
+    ];
+    const instructions: [string, CounterInstruction, TransactionInstruction["keys"]][] = [
+        ["Increment", { Increment: 1n }, updateKeys],
+        ["Set", { Set: { Unsigned: 5n } }, updateKeys],
+        ["Reset", { Reset: {} }, updateKeys],
+        ["CompareAndSet", { CompareAndSet: { expected: { Unsigned: 0n }, new: { Unsigned: 1n } } }, updateKeys],
+        ["Get", { Get: {} }, [{ pubkey: counter, isSigner: false, isWritable: false }]],
+    ];
+
+    const results: Record<string, number> = {};
+    for (const [name, instruction, keys] of instructions) {
+        results[name] = await computeUnits(connection, instruction, keys);
+    }
+    return results;
+}
+
+const results = await measure(RPC_URL);
+if (BASELINE_RPC_URL) {
+    const baseline = await measure(BASELINE_RPC_URL);
+    console.table(
+        Object.fromEntries(
+            Object.entries(results).map(([name, units]) => [
+                name,
+                { baseline: baseline[name]!, current: units, saved: baseline[name]! - units },
+            ])
+        )
+    );
+} else {
+    console.table(results);
+}
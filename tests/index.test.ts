@@ -10,7 +10,31 @@ import {
     sendAndConfirmTransaction
 } from "@solana/web3.js";
 import { expect, test, describe, beforeAll, afterAll } from "bun:test";
-import { COUNTER_SIZE, CounterAccount, instructionSchema, schema, type CounterInstruction } from "./types";
+import {
+    COUNTER_DISCRIMINATOR,
+    COUNTER_SEED,
+    COUNTER_SIZE,
+    CounterAccount,
+    CounterError,
+    LAYOUT_VERSION,
+    LEGACY_COUNTER_SIZE,
+    MULTISIG_SEED,
+    ROLE_BITS,
+    ROLE_ENTRY_SIZE,
+    counterValueToNumber,
+    instructionSchema,
+    parseCounterEvents,
+    parseRoleEntries,
+    returnDataSchema,
+    schema,
+    type CounterEvent,
+    type CounterInstruction,
+    type CounterReturnData,
+    type Op,
+    type CounterKind,
+    type OverflowPolicy,
+    type Role
+} from "./types";
 
 // Configuration
 const PROGRAM_ID = new PublicKey("CC6Jc1wkfdyyiRGQAGy8UVXXZdb9LDRbc7hJnrxdC44U");
@@ -88,6 +112,96 @@ async function createProgramAccount(
     }
 }
 
+/**
+ * Derives the counter PDA for an authority and seed
+ * @param authority - The counter authority (the payer at initialization)
+ * @param seed - Extra seed distinguishing counters of the same authority
+ * @returns The counter address and its bump
+ */
+function findCounterAddress(authority: PublicKey, seed: Uint8Array): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([COUNTER_SEED, authority.toBuffer(), seed], PROGRAM_ID);
+}
+
+/**
+ * Creates a counter PDA through the program's Initialize instruction
+ * @param connection - Solana connection instance
+ * @param payer - Account that pays for the counter and becomes its authority
+ * @param seed - Extra seed distinguishing counters of the same authority
+ * @param counterAccount - Address to initialize, defaults to the derived PDA
+ * @param kind - Whether the counter is unsigned (u64) or signed (i64)
+ * @param policy - What increments and decrements do past the counter's range
+ * @param monotonic - Whether the counter may never decrease
+ * @returns The counter address
+ */
+async function initializeCounter(
+    connection: Connection,
+    payer: Keypair,
+    seed: Uint8Array,
+    counterAccount: PublicKey = findCounterAddress(payer.publicKey, seed)[0],
+    kind: CounterKind = { Unsigned: {} },
+    policy: OverflowPolicy = { Checked: {} },
+    monotonic: boolean = false
+): Promise<PublicKey> {
+    const ix = new TransactionInstruction({
+        keys: [
+            { pubkey: payer.publicKey, isSigner: true, isWritable: true },
+            { pubkey: counterAccount, isSigner: false, isWritable: true },
+            { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction({ Initialize: { seed, kind, policy, monotonic } }),
+    });
+
+    await sendAndConfirmTransaction(connection, new Transaction().add(ix), [payer]);
+    return counterAccount;
+}
+
+/**
+ * Builds the program's Close instruction
+ * @param counterAccount - The counter to close
+ * @param authority - The counter authority
+ * @param destination - Account receiving the counter's lamports
+ * @returns The Close instruction
+ */
+function closeCounterInstruction(
+    counterAccount: PublicKey,
+    authority: PublicKey,
+    destination: PublicKey
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: counterAccount, isSigner: false, isWritable: true },
+            { pubkey: authority, isSigner: true, isWritable: false },
+            { pubkey: destination, isSigner: false, isWritable: true },
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction({ Close: {} }),
+    });
+}
+
+/**
+ * Builds the program's Migrate instruction
+ * @param counterAccount - The counter to migrate
+ * @param payer - Account topping up rent, and the new authority of legacy counters
+ * @param counterSigns - Whether the (legacy keypair) counter signs the transaction
+ * @returns The Migrate instruction
+ */
+function migrateCounterInstruction(
+    counterAccount: PublicKey,
+    payer: PublicKey,
+    counterSigns: boolean
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: counterAccount, isSigner: counterSigns, isWritable: true },
+            { pubkey: payer, isSigner: true, isWritable: true },
+            { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction({ Migrate: {} }),
+    });
+}
+
 /**
  * Serializes a counter instruction using borsh
  * @param instruction - The instruction to serialize
@@ -97,11 +211,178 @@ function serializeInstruction(instruction: CounterInstruction): Buffer {
     return Buffer.from(borsh.serialize(instructionSchema, instruction));
 }
 
+/**
+ * Builds an instruction over several counters sharing one authority
+ * @param instruction - A Batch or UpdateMany instruction
+ * @param counters - Counter accounts, in the order the instruction refers to them
+ * @param authority - Common authority of the counters
+ * @param approvers - Signers approving the instruction when the authority is a multisig
+ * @returns The transaction instruction
+ */
+function multiCounterInstruction(
+    instruction: CounterInstruction,
+    counters: PublicKey[],
+    authority: PublicKey,
+    approvers: PublicKey[] = []
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            ...approvers.map((pubkey) => ({ pubkey, isSigner: true, isWritable: false })),
+            ...counters.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
+            { pubkey: authority, isSigner: approvers.length === 0, isWritable: false },
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction(instruction),
+    });
+}
+
+/**
+ * Builds a Batch instruction over several counters
+ * @param counters - Counter accounts, indexed by the ops
+ * @param authority - Common authority of the counters
+ * @param ops - Operations to apply, in order
+ * @returns The Batch instruction
+ */
+function batchInstruction(
+    counters: PublicKey[],
+    authority: PublicKey,
+    ops: Op[]
+): TransactionInstruction {
+    return multiCounterInstruction({ Batch: ops }, counters, authority);
+}
+
+/**
+ * Builds a Transfer instruction between two counters
+ * @param source - Counter the amount is taken from
+ * @param destination - Counter the amount is added to
+ * @param authority - Authority of both counters, or a holder of the roles they require
+ * @param amount - Amount to move
+ * @returns The Transfer instruction
+ */
+function transferInstruction(
+    source: PublicKey,
+    destination: PublicKey,
+    authority: PublicKey,
+    amount: bigint | number
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: source, isSigner: false, isWritable: true },
+            { pubkey: destination, isSigner: false, isWritable: true },
+            { pubkey: authority, isSigner: true, isWritable: false },
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction({ Transfer: { amount } }),
+    });
+}
+
+/**
+ * Builds a GrantRole instruction, paid for by the granting signer
+ * @param counter - The counter account
+ * @param granter - The authority, or an admin for non-admin roles
+ * @param member - Account receiving the role
+ * @param role - Role to grant
+ * @returns The GrantRole instruction
+ */
+function grantRoleInstruction(
+    counter: PublicKey,
+    granter: PublicKey,
+    member: PublicKey,
+    role: Role
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: counter, isSigner: false, isWritable: true },
+            { pubkey: granter, isSigner: true, isWritable: false },
+            { pubkey: granter, isSigner: true, isWritable: true },
+            { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction({ GrantRole: { member: member.toBytes(), role } }),
+    });
+}
+
+/**
+ * Derives the multisig that can become a counter's authority
+ * @param counter - The counter account
+ * @returns The multisig address
+ */
+function findMultisigAddress(counter: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync([MULTISIG_SEED, counter.toBuffer()], PROGRAM_ID)[0];
+}
+
+/**
+ * Builds a SetMultisig instruction, paid for by the payer
+ * @param counter - The counter account
+ * @param authority - Current authority, or the multisig itself if `approvers` are given
+ * @param payer - Account paying for the multisig account
+ * @param signers - Signers of the new multisig, each of which signs
+ * @param threshold - Signatures the new multisig requires
+ * @param approvers - Signers of the current multisig approving the change
+ * @returns The SetMultisig instruction
+ */
+function setMultisigInstruction(
+    counter: PublicKey,
+    authority: PublicKey,
+    payer: PublicKey,
+    signers: PublicKey[],
+    threshold: number,
+    approvers: PublicKey[] = []
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: counter, isSigner: false, isWritable: true },
+            { pubkey: authority, isSigner: approvers.length === 0, isWritable: false },
+            { pubkey: findMultisigAddress(counter), isSigner: false, isWritable: true },
+            { pubkey: payer, isSigner: true, isWritable: true },
+            { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+            ...approvers.map((pubkey) => ({ pubkey, isSigner: true, isWritable: false })),
+            ...signers.map((pubkey) => ({ pubkey, isSigner: true, isWritable: false })),
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction({
+            SetMultisig: { signers: signers.map((signer) => signer.toBytes()), threshold },
+        }),
+    });
+}
+
+/**
+ * Builds a single-counter instruction approved by the counter's multisig
+ * @param instruction - The instruction to send
+ * @param counter - The counter account, whose authority is its multisig
+ * @param approvers - Multisig signers approving the instruction
+ * @returns The instruction
+ */
+function multisigInstruction(
+    instruction: CounterInstruction,
+    counter: PublicKey,
+    approvers: PublicKey[]
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: counter, isSigner: false, isWritable: true },
+            { pubkey: findMultisigAddress(counter), isSigner: false, isWritable: false },
+            ...approvers.map((pubkey) => ({ pubkey, isSigner: true, isWritable: false })),
+        ],
+        programId: PROGRAM_ID,
+        data: serializeInstruction(instruction),
+    });
+}
+
+/**
+ * Formats the log fragment the runtime emits for a `CounterError`
+ * @param error - The expected counter error
+ * @returns Substring of the transaction error message
+ */
+function customError(error: CounterError): string {
+    return `custom program error: 0x${error.toString(16)}`;
+}
+
 /**
  * Executes a counter instruction and returns the updated counter value
  * @param connection - Solana connection
  * @param instruction - The instruction to execute
- * @param payer - Account that pays for the transaction
+ * @param payer - Account that pays for the transaction and signs as the counter authority
  * @param counterAccount - The counter account to modify
  * @returns Updated counter value
  */
@@ -114,7 +395,10 @@ async function executeCounterInstruction(
     const instructionData = serializeInstruction(instruction);
     
     const ix = new TransactionInstruction({
-        keys: [{ pubkey: counterAccount, isSigner: false, isWritable: true }],
+        keys: [
+            { pubkey: counterAccount, isSigner: false, isWritable: true },
+            { pubkey: payer.publicKey, isSigner: true, isWritable: false },
+        ],
         programId: PROGRAM_ID,
         data: instructionData,
     });
@@ -128,8 +412,65 @@ async function executeCounterInstruction(
         throw new Error("Counter account not found after instruction execution");
     }
     
-    const counter = borsh.deserialize(schema, updatedInfo.data) as CounterAccount;
-    return counter.count;
+    const counter = borsh.deserialize(schema, updatedInfo.data.subarray(0, COUNTER_SIZE)) as CounterAccount;
+    return counterValueToNumber(counter.count);
+}
+
+/**
+ * Reads and decodes the full counter account
+ * @param connection - Solana connection
+ * @param counterAccount - The counter account to read
+ * @returns The decoded counter account
+ */
+async function getCounter(
+    connection: Connection,
+    counterAccount: PublicKey
+): Promise<CounterAccount> {
+    const accountInfo = await connection.getAccountInfo(counterAccount);
+    if (!accountInfo) {
+        throw new Error("Counter account not found");
+    }
+
+    return borsh.deserialize(schema, accountInfo.data.subarray(0, COUNTER_SIZE)) as CounterAccount;
+}
+
+/**
+ * Simulates a counter instruction and decodes its return data
+ * @param connection - Solana connection
+ * @param ix - The instruction to simulate
+ * @param payer - Fee payer and signer of the simulated transaction
+ * @returns The decoded return data
+ */
+async function simulateReturnData(
+    connection: Connection,
+    ix: TransactionInstruction,
+    payer: Keypair
+): Promise<CounterReturnData> {
+    const { value } = await connection.simulateTransaction(new Transaction().add(ix), [payer]);
+    if (value.err || !value.returnData) {
+        throw new Error(`Simulation failed: ${JSON.stringify(value.err)}`);
+    }
+    expect(value.returnData.programId).toBe(PROGRAM_ID.toBase58());
+
+    const data = Buffer.from(value.returnData.data[0], "base64");
+    return borsh.deserialize(returnDataSchema, data) as CounterReturnData;
+}
+
+/**
+ * Sends a single instruction and returns the counter events it emitted
+ * @param connection - Solana connection
+ * @param ix - The instruction to send
+ * @param signers - Signers of the transaction, fee payer first
+ * @returns The decoded events
+ */
+async function sendAndCollectEvents(
+    connection: Connection,
+    ix: TransactionInstruction,
+    signers: Keypair[]
+): Promise<CounterEvent[]> {
+    const signature = await sendAndConfirmTransaction(connection, new Transaction().add(ix), signers);
+    const tx = await connection.getTransaction(signature, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+    return parseCounterEvents(tx!.meta!.logMessages!);
 }
 
 /**
@@ -147,14 +488,15 @@ async function getCounterValue(
         throw new Error("Counter account not found");
     }
     
-    const counter = borsh.deserialize(schema, accountInfo.data) as CounterAccount;
-    return counter.count;
+    const counter = borsh.deserialize(schema, accountInfo.data.subarray(0, COUNTER_SIZE)) as CounterAccount;
+    return counterValueToNumber(counter.count);
 }
 
 describe("Counter Program Tests", () => {
     let connection: Connection;
     let adminAccount: Keypair;
-    let dataAccount: Keypair;
+    let dataAccount: PublicKey;
+    const counterSeed = new TextEncoder().encode("main");
 
     beforeAll(async () => {
         // Initialize connection
@@ -162,7 +504,6 @@ describe("Counter Program Tests", () => {
 
         // Generate fresh keypairs for each test run
         adminAccount = Keypair.generate();
-        dataAccount = Keypair.generate();
 
         // Ensure connection is working
         try {
@@ -192,17 +533,11 @@ describe("Counter Program Tests", () => {
             throw new Error("Failed to fund admin account");
         }
 
-        // Create the data account
-        await createProgramAccount(
-            connection,
-            adminAccount,
-            dataAccount,
-            COUNTER_SIZE,
-            PROGRAM_ID
-        );
+        // Create the counter PDA
+        dataAccount = await initializeCounter(connection, adminAccount, counterSeed);
 
         // Verify data account was created
-        const dataAccountInfo = await connection.getAccountInfo(dataAccount.publicKey);
+        const dataAccountInfo = await connection.getAccountInfo(dataAccount);
         if (!dataAccountInfo) {
             throw new Error("Failed to create data account");
         }
@@ -211,7 +546,7 @@ describe("Counter Program Tests", () => {
     describe("Account Initialization", () => {
         test("should initialize counter account with zero value", async () => {
             // Verify data account was created
-            const dataAccountInfo = await connection.getAccountInfo(dataAccount.publicKey);
+            const dataAccountInfo = await connection.getAccountInfo(dataAccount);
             expect(dataAccountInfo).not.toBeNull();
             expect(dataAccountInfo!.owner).toEqual(PROGRAM_ID);
             expect(dataAccountInfo!.data.length).toBe(COUNTER_SIZE);
@@ -219,12 +554,54 @@ describe("Counter Program Tests", () => {
             // Deserialize and verify initial counter value
             const counterAccount = borsh.deserialize(schema, dataAccountInfo!.data) as CounterAccount;
             expect(counterAccount).toBeTruthy();
-            expect(counterAccount.count).toBe(0);
+            expect(Array.from(counterAccount.discriminator)).toEqual(Array.from(COUNTER_DISCRIMINATOR));
+            expect(counterAccount.layoutVersion).toBe(LAYOUT_VERSION);
+            expect(counterAccount.reserved.every((byte) => byte === 0)).toBe(true);
+            expect(counterAccount.count).toEqual({ Unsigned: 0n });
+            expect(counterAccount.policy).toEqual({ Checked: {} });
+            expect(counterAccount.bounds.mode).toEqual({ Unbounded: {} });
+            expect(counterAccount.monotonic).toBe(false);
+            expect(counterAccount.pendingAuthority.every((byte) => byte === 0)).toBe(true);
+            expect(counterAccount.authorityMode).toEqual({ Authority: {} });
+            expect(new PublicKey(counterAccount.authority).equals(adminAccount.publicKey)).toBe(true);
+            expect(counterAccount.bump).toBe(findCounterAddress(adminAccount.publicKey, counterSeed)[1]);
 
             // Verify account is rent exempt
             const minimumBalance = await connection.getMinimumBalanceForRentExemption(COUNTER_SIZE);
             expect(dataAccountInfo!.lamports).toBeGreaterThanOrEqual(minimumBalance);
         }, TEST_TIMEOUT);
+
+        test("should refuse to re-initialize an existing counter", async () => {
+            await expect(
+                initializeCounter(connection, adminAccount, counterSeed)
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+
+        test("should refuse to initialize an address that is not the derived PDA", async () => {
+            await expect(
+                initializeCounter(connection, adminAccount, counterSeed, Keypair.generate().publicKey)
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+
+        test("should initialize independent counters for different seeds", async () => {
+            const otherCounter = await initializeCounter(
+                connection,
+                adminAccount,
+                new TextEncoder().encode("secondary")
+            );
+
+            expect(otherCounter.equals(dataAccount)).toBe(false);
+            expect(await getCounterValue(connection, otherCounter)).toBe(0);
+        }, TEST_TIMEOUT);
+
+        test("should reject updates to an uninitialized program-owned account", async () => {
+            const rawAccount = Keypair.generate();
+            await createProgramAccount(connection, adminAccount, rawAccount, COUNTER_SIZE, PROGRAM_ID);
+
+            await expect(
+                executeCounterInstruction(connection, { Increment: 1 }, adminAccount, rawAccount.publicKey)
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
     });
 
     describe("Counter Operations", () => {
@@ -234,175 +611,1677 @@ describe("Counter Program Tests", () => {
                 connection,
                 { Increment: incrementValue },
                 adminAccount,
-                dataAccount.publicKey
+                dataAccount
             );
             
             expect(newValue).toBe(incrementValue);
         }, TEST_TIMEOUT);
 
         test("should increment the counter by a large value", async () => {
-            const currentValue = await getCounterValue(connection, dataAccount.publicKey);
+            const currentValue = await getCounterValue(connection, dataAccount);
             const incrementValue = 1000000;
             
             const newValue = await executeCounterInstruction(
                 connection,
                 { Increment: incrementValue },
                 adminAccount,
-                dataAccount.publicKey
+                dataAccount
             );
             
             expect(newValue).toBe(currentValue + incrementValue);
         }, TEST_TIMEOUT);
 
         test("should decrement the counter", async () => {
-            const currentValue = await getCounterValue(connection, dataAccount.publicKey);
+            const currentValue = await getCounterValue(connection, dataAccount);
             const decrementValue = 3;
             
             const newValue = await executeCounterInstruction(
                 connection,
                 { Decrement: decrementValue },
                 adminAccount,
-                dataAccount.publicKey
+                dataAccount
             );
             
             expect(newValue).toBe(currentValue - decrementValue);
         }, TEST_TIMEOUT);
 
         test("should handle multiple increment operations", async () => {
-            const initialValue = await getCounterValue(connection, dataAccount.publicKey);
+            const initialValue = await getCounterValue(connection, dataAccount);
             
             // Perform multiple increments
-            await executeCounterInstruction(connection, { Increment: 10 }, adminAccount, dataAccount.publicKey);
-            await executeCounterInstruction(connection, { Increment: 20 }, adminAccount, dataAccount.publicKey);
-            const finalValue = await executeCounterInstruction(connection, { Increment: 30 }, adminAccount, dataAccount.publicKey);
+            await executeCounterInstruction(connection, { Increment: 10 }, adminAccount, dataAccount);
+            await executeCounterInstruction(connection, { Increment: 20 }, adminAccount, dataAccount);
+            const finalValue = await executeCounterInstruction(connection, { Increment: 30 }, adminAccount, dataAccount);
             
             expect(finalValue).toBe(initialValue + 10 + 20 + 30);
         }, TEST_TIMEOUT);
 
         test("should handle mixed increment and decrement operations", async () => {
-            const initialValue = await getCounterValue(connection, dataAccount.publicKey);
+            const initialValue = await getCounterValue(connection, dataAccount);
             
             // Mix of operations
-            await executeCounterInstruction(connection, { Increment: 50 }, adminAccount, dataAccount.publicKey);
-            await executeCounterInstruction(connection, { Decrement: 20 }, adminAccount, dataAccount.publicKey);
-            const finalValue = await executeCounterInstruction(connection, { Increment: 15 }, adminAccount, dataAccount.publicKey);
+            await executeCounterInstruction(connection, { Increment: 50 }, adminAccount, dataAccount);
+            await executeCounterInstruction(connection, { Decrement: 20 }, adminAccount, dataAccount);
+            const finalValue = await executeCounterInstruction(connection, { Increment: 15 }, adminAccount, dataAccount);
             
             expect(finalValue).toBe(initialValue + 50 - 20 + 15);
         }, TEST_TIMEOUT);
 
         test("should increment by 1", async () => {
-            const currentValue = await getCounterValue(connection, dataAccount.publicKey);
+            const currentValue = await getCounterValue(connection, dataAccount);
             
             const newValue = await executeCounterInstruction(
                 connection,
                 { Increment: 1 },
                 adminAccount,
-                dataAccount.publicKey
+                dataAccount
             );
             
             expect(newValue).toBe(currentValue + 1);
         }, TEST_TIMEOUT);
 
         test("should decrement by 1", async () => {
-            const currentValue = await getCounterValue(connection, dataAccount.publicKey);
+            const currentValue = await getCounterValue(connection, dataAccount);
             
             const newValue = await executeCounterInstruction(
                 connection,
                 { Decrement: 1 },
                 adminAccount,
-                dataAccount.publicKey
+                dataAccount
             );
             
             expect(newValue).toBe(currentValue - 1);
         }, TEST_TIMEOUT);
 
         test("should handle zero increment", async () => {
-            const currentValue = await getCounterValue(connection, dataAccount.publicKey);
+            const currentValue = await getCounterValue(connection, dataAccount);
             
             const newValue = await executeCounterInstruction(
                 connection,
                 { Increment: 0 },
                 adminAccount,
-                dataAccount.publicKey
+                dataAccount
             );
             
             expect(newValue).toBe(currentValue);
         }, TEST_TIMEOUT);
 
         test("should handle zero decrement", async () => {
-            const currentValue = await getCounterValue(connection, dataAccount.publicKey);
+            const currentValue = await getCounterValue(connection, dataAccount);
             
             const newValue = await executeCounterInstruction(
                 connection,
                 { Decrement: 0 },
                 adminAccount,
-                dataAccount.publicKey
+                dataAccount
             );
             
             expect(newValue).toBe(currentValue);
         }, TEST_TIMEOUT);
     });
 
-    describe("Edge Cases and Error Handling", () => {
-        test("should handle maximum u32 increment", async () => {
-            // Reset to a known state first
-            const currentValue = await getCounterValue(connection, dataAccount.publicKey);
-            
-            // Set counter to a value that allows for large increment without overflow
-            const maxU32 = 4294967295; // 2^32 - 1
-            const targetValue = 1000; // Set to a reasonable value for testing
-            
-            if (currentValue !== targetValue) {
-                if (currentValue < targetValue) {
-                    await executeCounterInstruction(
-                        connection,
-                        { Increment: targetValue - currentValue },
-                        adminAccount,
-                        dataAccount.publicKey
-                    );
-                } else {
-                    await executeCounterInstruction(
-                        connection,
-                        { Decrement: currentValue - targetValue },
-                        adminAccount,
-                        dataAccount.publicKey
-                    );
-                }
-            }
-            
-            // Now test large increment
-            const largeIncrement = 1000000;
-            const newValue = await executeCounterInstruction(
+    describe("Wide and Signed Counters", () => {
+        test("should count beyond u32", async () => {
+            const wide = await initializeCounter(connection, adminAccount, new TextEncoder().encode("wide"));
+
+            await executeCounterInstruction(connection, { Increment: 2n ** 40n }, adminAccount, wide);
+
+            expect((await getCounter(connection, wide)).count).toEqual({ Unsigned: 2n ** 40n });
+        }, TEST_TIMEOUT);
+
+        test("should go negative on a signed counter", async () => {
+            const signed = await initializeCounter(
                 connection,
-                { Increment: largeIncrement },
                 adminAccount,
-                dataAccount.publicKey
+                new TextEncoder().encode("signed"),
+                undefined,
+                { Signed: {} }
             );
-            
-            expect(newValue).toBe(targetValue + largeIncrement);
+            expect((await getCounter(connection, signed)).count).toEqual({ Signed: 0n });
+
+            const newValue = await executeCounterInstruction(connection, { Decrement: 5 }, adminAccount, signed);
+            expect(newValue).toBe(-5);
+
+            await expect(
+                executeCounterInstruction(connection, { Decrement: 2n ** 63n }, adminAccount, signed)
+            ).rejects.toThrow(customError(CounterError.Underflow));
+            await expect(
+                executeCounterInstruction(connection, { Set: { Unsigned: 1 } }, adminAccount, signed)
+            ).rejects.toThrow(customError(CounterError.KindMismatch));
+
+            expect(
+                await executeCounterInstruction(connection, { Set: { Signed: -42 } }, adminAccount, signed)
+            ).toBe(-42);
         }, TEST_TIMEOUT);
+    });
 
-        test("should handle invalid account data gracefully", async () => {
-            const nonExistentAccount = Keypair.generate();
-            const accountInfo = await connection.getAccountInfo(nonExistentAccount.publicKey);
-            expect(accountInfo).toBeNull();
+    describe("Overflow Policies", () => {
+        const MAX_U64 = 2n ** 64n - 1n;
+
+        test("should clamp a saturating counter at its bounds", async () => {
+            const saturating = await initializeCounter(
+                connection,
+                adminAccount,
+                new TextEncoder().encode("saturating"),
+                undefined,
+                { Unsigned: {} },
+                { Saturating: {} }
+            );
+
+            await executeCounterInstruction(connection, { Increment: MAX_U64 }, adminAccount, saturating);
+            await executeCounterInstruction(connection, { Increment: 10 }, adminAccount, saturating);
+            expect((await getCounter(connection, saturating)).count).toEqual({ Unsigned: MAX_U64 });
+
+            await executeCounterInstruction(connection, { Decrement: MAX_U64 }, adminAccount, saturating);
+            await executeCounterInstruction(connection, { Decrement: 10 }, adminAccount, saturating);
+            expect((await getCounter(connection, saturating)).count).toEqual({ Unsigned: 0n });
+        }, TEST_TIMEOUT);
+
+        test("should wrap a wrapping counter around its width", async () => {
+            const wrapping = await initializeCounter(
+                connection,
+                adminAccount,
+                new TextEncoder().encode("wrapping"),
+                undefined,
+                { Signed: {} },
+                { Wrapping: {} }
+            );
+
+            await executeCounterInstruction(connection, { Increment: 2n ** 63n - 1n }, adminAccount, wrapping);
+            await executeCounterInstruction(connection, { Increment: 1 }, adminAccount, wrapping);
+            expect((await getCounter(connection, wrapping)).count).toEqual({ Signed: -(2n ** 63n) });
+        }, TEST_TIMEOUT);
+
+        test("should report the applied policy in the log", async () => {
+            const ix = new TransactionInstruction({
+                keys: [
+                    { pubkey: dataAccount, isSigner: false, isWritable: true },
+                    { pubkey: adminAccount.publicKey, isSigner: true, isWritable: false },
+                ],
+                programId: PROGRAM_ID,
+                data: serializeInstruction({ Increment: 1 }),
+            });
+            const signature = await sendAndConfirmTransaction(connection, new Transaction().add(ix), [adminAccount]);
+
+            const tx = await connection.getTransaction(signature, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+            expect(tx!.meta!.logMessages!.join("\n")).toContain("Adding 1 with Checked policy");
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Bounds", () => {
+        let bounded: PublicKey;
+
+        beforeAll(async () => {
+            bounded = await initializeCounter(connection, adminAccount, new TextEncoder().encode("bounded"));
         });
 
-        test("should fail when trying to execute instruction on non-existent account", async () => {
-            const nonExistentAccount = Keypair.generate();
-            
+        test("should reject updates outside the bounds in reject mode", async () => {
+            await executeCounterInstruction(
+                connection,
+                { SetBounds: { mode: { Reject: {} }, min: { Unsigned: 0 }, max: { Unsigned: 10 } } },
+                adminAccount,
+                bounded
+            );
+
+            await expect(
+                executeCounterInstruction(connection, { Increment: 11 }, adminAccount, bounded)
+            ).rejects.toThrow(customError(CounterError.OutOfBounds));
+            await expect(
+                executeCounterInstruction(connection, { Set: { Unsigned: 20 } }, adminAccount, bounded)
+            ).rejects.toThrow(customError(CounterError.OutOfBounds));
+            expect(await executeCounterInstruction(connection, { Increment: 10 }, adminAccount, bounded)).toBe(10);
+        }, TEST_TIMEOUT);
+
+        test("should clamp updates into the bounds in clamp mode", async () => {
+            await executeCounterInstruction(
+                connection,
+                { SetBounds: { mode: { Clamp: {} }, min: { Unsigned: 2 }, max: { Unsigned: 8 } } },
+                adminAccount,
+                bounded
+            );
+            expect(await getCounterValue(connection, bounded)).toBe(8);
+
+            expect(await executeCounterInstruction(connection, { Increment: 100 }, adminAccount, bounded)).toBe(8);
+            expect(await executeCounterInstruction(connection, { Decrement: 7 }, adminAccount, bounded)).toBe(2);
+            expect(await executeCounterInstruction(connection, { Reset: {} }, adminAccount, bounded)).toBe(2);
+        }, TEST_TIMEOUT);
+
+        test("should reject bounds with min above max", async () => {
             await expect(
                 executeCounterInstruction(
                     connection,
-                    { Increment: 1 },
+                    { SetBounds: { mode: { Reject: {} }, min: { Unsigned: 5 }, max: { Unsigned: 1 } } },
                     adminAccount,
-                    nonExistentAccount.publicKey
+                    bounded
+                )
+            ).rejects.toThrow(customError(CounterError.InvalidBounds));
+        }, TEST_TIMEOUT);
+
+        test("should reject bound changes by a non-authority", async () => {
+            const stranger = Keypair.generate();
+            await transferSol(connection, 0.1, stranger.publicKey);
+
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { SetBounds: { mode: { Unbounded: {} }, min: { Unsigned: 0 }, max: { Unsigned: 0 } } },
+                    stranger,
+                    bounded
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Monotonic Counters", () => {
+        let sequence: PublicKey;
+
+        beforeAll(async () => {
+            sequence = await initializeCounter(
+                connection,
+                adminAccount,
+                new TextEncoder().encode("sequence"),
+                undefined,
+                { Unsigned: {} },
+                { Checked: {} },
+                true
+            );
+            await executeCounterInstruction(connection, { Increment: 10 }, adminAccount, sequence);
+        });
+
+        test("should record the monotonic flag", async () => {
+            expect((await getCounter(connection, sequence)).monotonic).toBe(true);
+        }, TEST_TIMEOUT);
+
+        test("should reject decrements and resets", async () => {
+            await expect(
+                executeCounterInstruction(connection, { Decrement: 1 }, adminAccount, sequence)
+            ).rejects.toThrow(customError(CounterError.MonotonicViolation));
+            await expect(
+                executeCounterInstruction(connection, { Reset: {} }, adminAccount, sequence)
+            ).rejects.toThrow(customError(CounterError.MonotonicViolation));
+            expect(await getCounterValue(connection, sequence)).toBe(10);
+        }, TEST_TIMEOUT);
+
+        test("should only allow setting a higher value", async () => {
+            await expect(
+                executeCounterInstruction(connection, { Set: { Unsigned: 9 } }, adminAccount, sequence)
+            ).rejects.toThrow(customError(CounterError.MonotonicViolation));
+            expect(
+                await executeCounterInstruction(connection, { Set: { Unsigned: 20 } }, adminAccount, sequence)
+            ).toBe(20);
+        }, TEST_TIMEOUT);
+
+        test("should not be closed and re-initialized at zero", async () => {
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction()
+                        .add(closeCounterInstruction(sequence, adminAccount.publicKey, adminAccount.publicKey))
+                        .add(new TransactionInstruction({
+                            keys: [
+                                { pubkey: adminAccount.publicKey, isSigner: true, isWritable: true },
+                                { pubkey: sequence, isSigner: false, isWritable: true },
+                                { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+                            ],
+                            programId: PROGRAM_ID,
+                            data: serializeInstruction({
+                                Initialize: {
+                                    seed: new TextEncoder().encode("sequence"),
+                                    kind: { Unsigned: {} },
+                                    policy: { Checked: {} },
+                                    monotonic: true,
+                                },
+                            }),
+                        })),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.MonotonicClose));
+            await expect(
+                initializeCounter(
+                    connection,
+                    adminAccount,
+                    new TextEncoder().encode("sequence"),
+                    undefined,
+                    { Unsigned: {} },
+                    { Checked: {} },
+                    true
                 )
             ).rejects.toThrow();
+            expect(await getCounterValue(connection, sequence)).toBe(20);
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Return Data", () => {
+        test("should return the previous and new value after an update", async () => {
+            const { count, version } = await getCounter(connection, dataAccount);
+
+            const returnData = await simulateReturnData(
+                connection,
+                new TransactionInstruction({
+                    keys: [
+                        { pubkey: dataAccount, isSigner: false, isWritable: true },
+                        { pubkey: adminAccount.publicKey, isSigner: true, isWritable: false },
+                    ],
+                    programId: PROGRAM_ID,
+                    data: serializeInstruction({ Increment: 3 }),
+                }),
+                adminAccount
+            );
+
+            expect(returnData.previous).toEqual(count);
+            expect(returnData.current).toEqual({ Unsigned: BigInt(counterValueToNumber(count) + 3) });
+            expect(returnData.version).toBe(version + 1n);
+        }, TEST_TIMEOUT);
+
+        test("should return the value from a read-only Get", async () => {
+            const { count, version } = await getCounter(connection, dataAccount);
+
+            const returnData = await simulateReturnData(
+                connection,
+                new TransactionInstruction({
+                    keys: [{ pubkey: dataAccount, isSigner: false, isWritable: false }],
+                    programId: PROGRAM_ID,
+                    data: serializeInstruction({ Get: {} }),
+                }),
+                adminAccount
+            );
+
+            expect(returnData).toEqual({ previous: count, current: count, version });
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Events", () => {
+        test("should emit CounterInitialized and Incremented events", async () => {
+            const seed = new TextEncoder().encode("events");
+            const [counter] = findCounterAddress(adminAccount.publicKey, seed);
+
+            const initEvents = await sendAndCollectEvents(
+                connection,
+                new TransactionInstruction({
+                    keys: [
+                        { pubkey: adminAccount.publicKey, isSigner: true, isWritable: true },
+                        { pubkey: counter, isSigner: false, isWritable: true },
+                        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+                    ],
+                    programId: PROGRAM_ID,
+                    data: serializeInstruction({
+                        Initialize: { seed, kind: { Unsigned: {} }, policy: { Checked: {} }, monotonic: false }
+                    }),
+                }),
+                [adminAccount]
+            );
+            expect(initEvents).toHaveLength(1);
+            const initialized = initEvents[0]!;
+            expect("CounterInitialized" in initialized).toBe(true);
+
+            const incrementEvents = await sendAndCollectEvents(
+                connection,
+                new TransactionInstruction({
+                    keys: [
+                        { pubkey: counter, isSigner: false, isWritable: true },
+                        { pubkey: adminAccount.publicKey, isSigner: true, isWritable: false },
+                    ],
+                    programId: PROGRAM_ID,
+                    data: serializeInstruction({ Increment: 4 }),
+                }),
+                [adminAccount]
+            );
+            expect(incrementEvents).toHaveLength(1);
+            const incremented = incrementEvents[0]!;
+            if (!("Incremented" in incremented)) {
+                throw new Error(`Unexpected event ${JSON.stringify(incremented)}`);
+            }
+            expect(new PublicKey(incremented.Incremented.counter).equals(counter)).toBe(true);
+            expect(incremented.Incremented.amount).toBe(4n);
+            expect(incremented.Incremented.previous).toEqual({ Unsigned: 0n });
+            expect(incremented.Incremented.current).toEqual({ Unsigned: 4n });
+        }, TEST_TIMEOUT);
+
+        test("should emit a Closed event", async () => {
+            const closable = await initializeCounter(connection, adminAccount, new TextEncoder().encode("events-close"));
+
+            const events = await sendAndCollectEvents(
+                connection,
+                closeCounterInstruction(closable, adminAccount.publicKey, adminAccount.publicKey),
+                [adminAccount]
+            );
+
+            expect(events).toHaveLength(1);
+            const closed = events[0]!;
+            if (!("Closed" in closed)) {
+                throw new Error(`Unexpected event ${JSON.stringify(closed)}`);
+            }
+            expect(new PublicKey(closed.Closed.destination).equals(adminAccount.publicKey)).toBe(true);
+            expect(closed.Closed.lamports).toBeGreaterThan(0n);
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Authority", () => {
+        test("should reject updates signed by another key", async () => {
+            const currentValue = await getCounterValue(connection, dataAccount);
+            const stranger = Keypair.generate();
+            await transferSol(connection, 0.1, stranger.publicKey);
+
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { Decrement: 1 },
+                    stranger,
+                    dataAccount
+                )
+            ).rejects.toThrow();
+            expect(await getCounterValue(connection, dataAccount)).toBe(currentValue);
+        }, TEST_TIMEOUT);
+
+        test("should reject updates without the authority signature", async () => {
+            const ix = new TransactionInstruction({
+                keys: [
+                    { pubkey: dataAccount, isSigner: false, isWritable: true },
+                    { pubkey: adminAccount.publicKey, isSigner: false, isWritable: false },
+                ],
+                programId: PROGRAM_ID,
+                data: serializeInstruction({ Increment: 1 }),
+            });
+
+            await expect(
+                sendAndConfirmTransaction(connection, new Transaction().add(ix), [fundingKeypair])
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Authority Transfer", () => {
+        let handover: PublicKey;
+        let successor: Keypair;
+
+        beforeAll(async () => {
+            handover = await initializeCounter(connection, adminAccount, new TextEncoder().encode("handover"));
+            successor = Keypair.generate();
+            await transferSol(connection, 0.1, successor.publicKey);
+        });
+
+        test("should record the proposed authority without handing over", async () => {
+            const events = await sendAndCollectEvents(
+                connection,
+                new TransactionInstruction({
+                    keys: [
+                        { pubkey: handover, isSigner: false, isWritable: true },
+                        { pubkey: adminAccount.publicKey, isSigner: true, isWritable: false },
+                    ],
+                    programId: PROGRAM_ID,
+                    data: serializeInstruction({ ProposeAuthority: successor.publicKey.toBytes() }),
+                }),
+                [adminAccount]
+            );
+
+            const counter = await getCounter(connection, handover);
+            expect(new PublicKey(counter.pendingAuthority).equals(successor.publicKey)).toBe(true);
+            expect(new PublicKey(counter.authority).equals(adminAccount.publicKey)).toBe(true);
+            expect(events).toHaveLength(1);
+            expect("AuthorityProposed" in events[0]!).toBe(true);
+        }, TEST_TIMEOUT);
+
+        test("should only let the pending authority accept", async () => {
+            await expect(
+                executeCounterInstruction(connection, { AcceptAuthority: {} }, adminAccount, handover)
+            ).rejects.toThrow();
+
+            await executeCounterInstruction(connection, { AcceptAuthority: {} }, successor, handover);
+            const counter = await getCounter(connection, handover);
+            expect(new PublicKey(counter.authority).equals(successor.publicKey)).toBe(true);
+            expect(counter.pendingAuthority.every((byte) => byte === 0)).toBe(true);
+
+            await expect(
+                executeCounterInstruction(connection, { Increment: 1 }, adminAccount, handover)
+            ).rejects.toThrow();
+            expect(await executeCounterInstruction(connection, { Increment: 1 }, successor, handover)).toBe(1);
+        }, TEST_TIMEOUT);
+
+        test("should cancel a pending transfer", async () => {
+            const mistyped = Keypair.generate().publicKey;
+            await executeCounterInstruction(
+                connection,
+                { ProposeAuthority: mistyped.toBytes() },
+                successor,
+                handover
+            );
+            await executeCounterInstruction(connection, { CancelAuthorityTransfer: {} }, successor, handover);
+
+            const counter = await getCounter(connection, handover);
+            expect(counter.pendingAuthority.every((byte) => byte === 0)).toBe(true);
+            await expect(
+                executeCounterInstruction(connection, { CancelAuthorityTransfer: {} }, successor, handover)
+            ).rejects.toThrow(customError(CounterError.NoPendingAuthority));
+            await expect(
+                executeCounterInstruction(connection, { AcceptAuthority: {} }, successor, handover)
+            ).rejects.toThrow(customError(CounterError.NoPendingAuthority));
+        }, TEST_TIMEOUT);
+
+        test("should only let the current authority propose", async () => {
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { ProposeAuthority: adminAccount.publicKey.toBytes() },
+                    adminAccount,
+                    handover
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Renounced Authority", () => {
+        let stranger: Keypair;
+
+        beforeAll(async () => {
+            stranger = Keypair.generate();
+            await transferSol(connection, 0.1, stranger.publicKey);
+        });
+
+        test("should let any signer update an open counter", async () => {
+            const open = await initializeCounter(connection, adminAccount, new TextEncoder().encode("open"));
+            const events = await sendAndCollectEvents(
+                connection,
+                new TransactionInstruction({
+                    keys: [
+                        { pubkey: open, isSigner: false, isWritable: true },
+                        { pubkey: adminAccount.publicKey, isSigner: true, isWritable: false },
+                    ],
+                    programId: PROGRAM_ID,
+                    data: serializeInstruction({ RenounceAuthority: { Open: {} } }),
+                }),
+                [adminAccount]
+            );
+
+            const counter = await getCounter(connection, open);
+            expect(counter.authorityMode).toEqual({ Open: {} });
+            expect(counter.authority.every((byte) => byte === 0)).toBe(true);
+            expect("AuthorityRenounced" in events[0]!).toBe(true);
+
+            expect(await executeCounterInstruction(connection, { Increment: 2 }, stranger, open)).toBe(2);
+            expect(await executeCounterInstruction(connection, { Increment: 3 }, adminAccount, open)).toBe(5);
+        }, TEST_TIMEOUT);
+
+        test("should reject settings changes on an open counter", async () => {
+            const open = await initializeCounter(connection, adminAccount, new TextEncoder().encode("open-settings"));
+            await executeCounterInstruction(connection, { RenounceAuthority: { Open: {} } }, adminAccount, open);
+
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { SetBounds: { mode: { Reject: {} }, min: { Unsigned: 0 }, max: { Unsigned: 1 } } },
+                    stranger,
+                    open
+                )
+            ).rejects.toThrow(customError(CounterError.NoAuthority));
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { ProposeAuthority: stranger.publicKey.toBytes() },
+                    stranger,
+                    open
+                )
+            ).rejects.toThrow(customError(CounterError.NoAuthority));
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(closeCounterInstruction(open, stranger.publicKey, stranger.publicKey)),
+                    [stranger]
+                )
+            ).rejects.toThrow(customError(CounterError.NoAuthority));
+        }, TEST_TIMEOUT);
+
+        test("should reject every update of an immutable counter", async () => {
+            const frozen = await initializeCounter(connection, adminAccount, new TextEncoder().encode("frozen"));
+            await executeCounterInstruction(connection, { Increment: 7 }, adminAccount, frozen);
+            await executeCounterInstruction(connection, { RenounceAuthority: { Immutable: {} } }, adminAccount, frozen);
+
+            for (const instruction of [{ Increment: 1 }, { Reset: {} }, { Set: { Unsigned: 1 } }] as CounterInstruction[]) {
+                await expect(
+                    executeCounterInstruction(connection, instruction, adminAccount, frozen)
+                ).rejects.toThrow(customError(CounterError.ImmutableCounter));
+            }
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(closeCounterInstruction(frozen, adminAccount.publicKey, adminAccount.publicKey)),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.ImmutableCounter));
+            expect(await getCounterValue(connection, frozen)).toBe(7);
+        }, TEST_TIMEOUT);
+
+        test("should reject renouncing into the authority mode", async () => {
+            const counter = await initializeCounter(connection, adminAccount, new TextEncoder().encode("renounce"));
+
+            await expect(
+                executeCounterInstruction(connection, { RenounceAuthority: { Authority: {} } }, adminAccount, counter)
+            ).rejects.toThrow();
+            await expect(
+                executeCounterInstruction(connection, { RenounceAuthority: { Open: {} } }, stranger, counter)
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Roles", () => {
+        let guarded: PublicKey;
+        let service: Keypair;
+        let admin: Keypair;
+
+        beforeAll(async () => {
+            guarded = await initializeCounter(connection, adminAccount, new TextEncoder().encode("guarded"));
+            service = Keypair.generate();
+            admin = Keypair.generate();
+            await transferSol(connection, 0.1, service.publicKey);
+            await transferSol(connection, 0.1, admin.publicKey);
+        });
+
+        test("should store granted roles in a table after the counter", async () => {
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(
+                    grantRoleInstruction(guarded, adminAccount.publicKey, service.publicKey, { Incrementer: {} })
+                ),
+                [adminAccount]
+            );
+
+            const accountInfo = await connection.getAccountInfo(guarded);
+            expect(accountInfo!.data.length).toBe(COUNTER_SIZE + ROLE_ENTRY_SIZE);
+            const [entry] = parseRoleEntries(accountInfo!.data);
+            expect(new PublicKey(entry!.member).equals(service.publicKey)).toBe(true);
+            expect(entry!.roles).toBe(ROLE_BITS.Incrementer);
+        }, TEST_TIMEOUT);
+
+        test("should let an incrementer increment but not decrement or reset", async () => {
+            expect(await executeCounterInstruction(connection, { Increment: 5 }, service, guarded)).toBe(5);
+
+            await expect(
+                executeCounterInstruction(connection, { Decrement: 1 }, service, guarded)
+            ).rejects.toThrow(customError(CounterError.MissingRole));
+            await expect(
+                executeCounterInstruction(connection, { Reset: {} }, service, guarded)
+            ).rejects.toThrow(customError(CounterError.MissingRole));
+        }, TEST_TIMEOUT);
+
+        test("should let an admin decrement, reset and grant other roles", async () => {
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(grantRoleInstruction(guarded, adminAccount.publicKey, admin.publicKey, { Admin: {} })),
+                [adminAccount]
+            );
+
+            expect(await executeCounterInstruction(connection, { Decrement: 2 }, admin, guarded)).toBe(3);
+            expect(await executeCounterInstruction(connection, { Reset: {} }, admin, guarded)).toBe(0);
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(grantRoleInstruction(guarded, admin.publicKey, service.publicKey, { Decrementer: {} })),
+                [admin]
+            );
+            const entries = parseRoleEntries((await connection.getAccountInfo(guarded))!.data);
+            expect(entries[0]!.roles).toBe(ROLE_BITS.Incrementer | ROLE_BITS.Decrementer);
+        }, TEST_TIMEOUT);
+
+        test("should only let the authority grant the admin role", async () => {
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(grantRoleInstruction(guarded, admin.publicKey, service.publicKey, { Admin: {} })),
+                    [admin]
+                )
+            ).rejects.toThrow();
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(grantRoleInstruction(guarded, service.publicKey, service.publicKey, { Admin: {} })),
+                    [service]
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+
+        test("should revoke roles and reuse the freed entry", async () => {
+            for (const role of [{ Incrementer: {} }, { Decrementer: {} }] as Role[]) {
+                await executeCounterInstruction(
+                    connection,
+                    { RevokeRole: { member: service.publicKey.toBytes(), role } },
+                    adminAccount,
+                    guarded
+                );
+            }
+            await expect(
+                executeCounterInstruction(connection, { Increment: 1 }, service, guarded)
+            ).rejects.toThrow(customError(CounterError.MissingRole));
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { RevokeRole: { member: service.publicKey.toBytes(), role: { Incrementer: {} } } },
+                    adminAccount,
+                    guarded
+                )
+            ).rejects.toThrow(customError(CounterError.MissingRole));
+
+            const newcomer = Keypair.generate().publicKey;
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(grantRoleInstruction(guarded, adminAccount.publicKey, newcomer, { Incrementer: {} })),
+                [adminAccount]
+            );
+            const accountInfo = await connection.getAccountInfo(guarded);
+            expect(accountInfo!.data.length).toBe(COUNTER_SIZE + 2 * ROLE_ENTRY_SIZE);
+            expect(new PublicKey(parseRoleEntries(accountInfo!.data)[0]!.member).equals(newcomer)).toBe(true);
+        }, TEST_TIMEOUT);
+
+        test("should revoke every admin when the authority changes", async () => {
+            const successor = Keypair.generate();
+            await transferSol(connection, 0.1, successor.publicKey);
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(
+                    grantRoleInstruction(guarded, adminAccount.publicKey, adminAccount.publicKey, { Admin: {} })
+                ),
+                [adminAccount]
+            );
+            await executeCounterInstruction(
+                connection,
+                { ProposeAuthority: successor.publicKey.toBytes() },
+                adminAccount,
+                guarded
+            );
+
+            const events = await sendAndCollectEvents(
+                connection,
+                new TransactionInstruction({
+                    keys: [
+                        { pubkey: guarded, isSigner: false, isWritable: true },
+                        { pubkey: successor.publicKey, isSigner: true, isWritable: false },
+                    ],
+                    programId: PROGRAM_ID,
+                    data: serializeInstruction({ AcceptAuthority: {} }),
+                }),
+                [successor]
+            );
+            expect(events).toHaveLength(3);
+            expect("AuthorityChanged" in events[0]!).toBe(true);
+            const revoked = events.slice(1).map((event) => {
+                const { RoleRevoked } = event as Extract<CounterEvent, { RoleRevoked: unknown }>;
+                expect(RoleRevoked.role).toEqual({ Admin: {} });
+                return new PublicKey(RoleRevoked.member).toBase58();
+            });
+            expect(revoked.sort()).toEqual([admin.publicKey.toBase58(), adminAccount.publicKey.toBase58()].sort());
+
+            for (const former of [adminAccount, admin]) {
+                await expect(
+                    executeCounterInstruction(connection, { Reset: {} }, former, guarded)
+                ).rejects.toThrow(customError(CounterError.MissingRole));
+            }
+            const entries = parseRoleEntries((await connection.getAccountInfo(guarded))!.data);
+            expect(entries[0]!.roles).toBe(ROLE_BITS.Incrementer);
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Multisig Authority", () => {
+        let treasury: PublicKey;
+        let multisig: PublicKey;
+        let members: Keypair[];
+
+        beforeAll(async () => {
+            treasury = await initializeCounter(connection, adminAccount, new TextEncoder().encode("treasury"));
+            multisig = findMultisigAddress(treasury);
+            members = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+        });
+
+        /** Builds a SetMultisig instruction in which `absent` does not sign */
+        function withoutSignature(signers: PublicKey[], threshold: number, absent: PublicKey): TransactionInstruction {
+            const ix = setMultisigInstruction(treasury, adminAccount.publicKey, adminAccount.publicKey, signers, threshold);
+            ix.keys = ix.keys.map((key) => (key.pubkey.equals(absent) ? { ...key, isSigner: false } : key));
+            return ix;
+        }
+
+        test("should require enough of the new signers to sign", async () => {
+            const mistyped = Keypair.generate().publicKey;
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(withoutSignature([members[0]!.publicKey, mistyped], 2, mistyped)),
+                    [adminAccount, members[0]!]
+                )
+            ).rejects.toThrow(customError(CounterError.NotEnoughSigners));
+
+            const counter = await getCounter(connection, treasury);
+            expect(new PublicKey(counter.authority).equals(adminAccount.publicKey)).toBe(true);
+        }, TEST_TIMEOUT);
+
+        test("should reject signers that can never sign", async () => {
+            for (const reserved of [PublicKey.default, treasury, multisig]) {
+                await expect(
+                    sendAndConfirmTransaction(
+                        connection,
+                        new Transaction().add(withoutSignature([members[0]!.publicKey, reserved], 1, reserved)),
+                        [adminAccount, members[0]!]
+                    )
+                ).rejects.toThrow(customError(CounterError.InvalidMultisig));
+            }
+        }, TEST_TIMEOUT);
+
+        test("should make a 2-of-3 multisig the counter's authority", async () => {
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(
+                    grantRoleInstruction(treasury, adminAccount.publicKey, adminAccount.publicKey, { Admin: {} })
+                ),
+                [adminAccount]
+            );
+            const events = await sendAndCollectEvents(
+                connection,
+                setMultisigInstruction(
+                    treasury,
+                    adminAccount.publicKey,
+                    adminAccount.publicKey,
+                    members.map((member) => member.publicKey),
+                    2
+                ),
+                [adminAccount, ...members]
+            );
+
+            const counter = await getCounter(connection, treasury);
+            expect(new PublicKey(counter.authority).equals(multisig)).toBe(true);
+            expect(events).toHaveLength(3);
+            expect("AuthorityChanged" in events[0]!).toBe(true);
+            const revoked = events[1]! as Extract<CounterEvent, { RoleRevoked: unknown }>;
+            expect(new PublicKey(revoked.RoleRevoked.member).equals(adminAccount.publicKey)).toBe(true);
+            const event = events[2]! as Extract<CounterEvent, { MultisigSet: unknown }>;
+            expect(event.MultisigSet.threshold).toBe(2);
+            expect(event.MultisigSet.signers).toHaveLength(3);
+        }, TEST_TIMEOUT);
+
+        test("should reject the former authority and its admin role", async () => {
+            await expect(
+                executeCounterInstruction(connection, { Increment: 1 }, adminAccount, treasury)
+            ).rejects.toThrow(customError(CounterError.MissingRole));
+        }, TEST_TIMEOUT);
+
+        test("should apply updates approved by enough signers", async () => {
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(
+                    multisigInstruction({ Increment: 7 }, treasury, [members[0]!.publicKey, members[2]!.publicKey])
+                ),
+                [adminAccount, members[0]!, members[2]!]
+            );
+            expect(await getCounterValue(connection, treasury)).toBe(7);
+        }, TEST_TIMEOUT);
+
+        test("should list the signers whose signatures are missing", async () => {
+            const outsider = Keypair.generate();
+            const ix = multisigInstruction({ Reset: {} }, treasury, [members[1]!.publicKey, outsider.publicKey]);
+            const { value } = await connection.simulateTransaction(
+                new Transaction().add(ix),
+                [adminAccount, members[1]!, outsider]
+            );
+            expect(value.err).not.toBeNull();
+            expect(value.logs).toContain(`Program log: Missing signature from ${members[0]!.publicKey.toBase58()}`);
+            expect(value.logs).toContain(`Program log: Missing signature from ${members[2]!.publicKey.toBase58()}`);
+
+            await expect(
+                sendAndConfirmTransaction(connection, new Transaction().add(ix), [adminAccount, members[1]!, outsider])
+            ).rejects.toThrow(customError(CounterError.NotEnoughSigners));
+            expect(await getCounterValue(connection, treasury)).toBe(7);
+        }, TEST_TIMEOUT);
+
+        test("should reject invalid signers and thresholds", async () => {
+            const approvers = [members[0]!, members[1]!];
+            const configs: [PublicKey[], number][] = [
+                [[members[0]!.publicKey], 0],
+                [[members[0]!.publicKey], 2],
+                [[members[0]!.publicKey, members[0]!.publicKey], 1],
+            ];
+            for (const [signers, threshold] of configs) {
+                await expect(
+                    sendAndConfirmTransaction(
+                        connection,
+                        new Transaction().add(
+                            setMultisigInstruction(
+                                treasury,
+                                multisig,
+                                adminAccount.publicKey,
+                                signers,
+                                threshold,
+                                approvers.map((approver) => approver.publicKey)
+                            )
+                        ),
+                        [adminAccount, ...approvers]
+                    )
+                ).rejects.toThrow(customError(CounterError.InvalidMultisig));
+            }
+        }, TEST_TIMEOUT);
+
+        test("should let the multisig replace its own signers", async () => {
+            const approvers = [members[1]!, members[2]!];
+            const events = await sendAndCollectEvents(
+                connection,
+                setMultisigInstruction(
+                    treasury,
+                    multisig,
+                    adminAccount.publicKey,
+                    [members[0]!.publicKey],
+                    1,
+                    approvers.map((approver) => approver.publicKey)
+                ),
+                [adminAccount, members[0]!, ...approvers]
+            );
+            expect(events).toHaveLength(1);
+            expect("MultisigSet" in events[0]!).toBe(true);
+
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(multisigInstruction({ Decrement: 2 }, treasury, [members[0]!.publicKey])),
+                [adminAccount, members[0]!]
+            );
+            expect(await getCounterValue(connection, treasury)).toBe(5);
+        }, TEST_TIMEOUT);
+
+        test("should apply batches approved by the multisig", async () => {
+            const batch = (approvers: PublicKey[]) =>
+                multiCounterInstruction(
+                    { Batch: [{ Increment: { counter: 0, amount: 3 } }] },
+                    [treasury],
+                    multisig,
+                    approvers
+                );
+
+            const outsider = Keypair.generate();
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(batch([outsider.publicKey])),
+                    [adminAccount, outsider]
+                )
+            ).rejects.toThrow(customError(CounterError.NotEnoughSigners));
+
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(batch([members[0]!.publicKey])),
+                [adminAccount, members[0]!]
+            );
+            expect(await getCounterValue(connection, treasury)).toBe(8);
+        }, TEST_TIMEOUT);
+
+        test("should close the multisig with the counter", async () => {
+            const destination = Keypair.generate().publicKey;
+            const close = (isWritable: boolean) =>
+                new TransactionInstruction({
+                    keys: [
+                        { pubkey: treasury, isSigner: false, isWritable: true },
+                        { pubkey: multisig, isSigner: false, isWritable },
+                        { pubkey: destination, isSigner: false, isWritable: true },
+                        { pubkey: members[0]!.publicKey, isSigner: true, isWritable: false },
+                    ],
+                    programId: PROGRAM_ID,
+                    data: serializeInstruction({ Close: {} }),
+                });
+
+            await expect(
+                sendAndConfirmTransaction(connection, new Transaction().add(close(false)), [adminAccount, members[0]!])
+            ).rejects.toThrow("Account is immutable");
+
+            const lamports = (await connection.getBalance(treasury)) + (await connection.getBalance(multisig));
+            await sendAndConfirmTransaction(connection, new Transaction().add(close(true)), [adminAccount, members[0]!]);
+            expect(await connection.getAccountInfo(treasury)).toBeNull();
+            expect(await connection.getAccountInfo(multisig)).toBeNull();
+            expect(await connection.getBalance(destination)).toBe(lamports);
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Reset and Set", () => {
+        test("should set the counter to an absolute value", async () => {
+            const newValue = await executeCounterInstruction(
+                connection,
+                { Set: { Unsigned: 500 } },
+                adminAccount,
+                dataAccount
+            );
+
+            expect(newValue).toBe(500);
+        }, TEST_TIMEOUT);
+
+        test("should reset the counter and log the previous value", async () => {
+            const previousValue = await getCounterValue(connection, dataAccount);
+
+            const ix = new TransactionInstruction({
+                keys: [
+                    { pubkey: dataAccount, isSigner: false, isWritable: true },
+                    { pubkey: adminAccount.publicKey, isSigner: true, isWritable: false },
+                ],
+                programId: PROGRAM_ID,
+                data: serializeInstruction({ Reset: {} }),
+            });
+            const signature = await sendAndConfirmTransaction(connection, new Transaction().add(ix), [adminAccount]);
+
+            expect(await getCounterValue(connection, dataAccount)).toBe(0);
+            const tx = await connection.getTransaction(signature, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+            expect(tx!.meta!.logMessages!.join("\n")).toContain(`Counter reset, previous value ${previousValue}`);
+        }, TEST_TIMEOUT);
+
+        test("should reject setting a value of the wrong kind", async () => {
+            await expect(
+                executeCounterInstruction(connection, { Set: { Signed: -1 } }, adminAccount, dataAccount)
+            ).rejects.toThrow(customError(CounterError.KindMismatch));
+        }, TEST_TIMEOUT);
+
+        test("should reject reset by a non-authority", async () => {
+            const stranger = Keypair.generate();
+            await transferSol(connection, 0.1, stranger.publicKey);
+
+            await expect(
+                executeCounterInstruction(connection, { Reset: {} }, stranger, dataAccount)
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Compare and Set", () => {
+        test("should bump the version on every write", async () => {
+            const before = await getCounter(connection, dataAccount);
+            await executeCounterInstruction(connection, { Increment: 1 }, adminAccount, dataAccount);
+            const after = await getCounter(connection, dataAccount);
+
+            expect(after.version).toBe(before.version + 1n);
+        }, TEST_TIMEOUT);
+
+        test("should swap when the expected value matches", async () => {
+            const currentValue = await getCounterValue(connection, dataAccount);
+
+            const newValue = await executeCounterInstruction(
+                connection,
+                { CompareAndSet: { expected: { Unsigned: currentValue }, new: { Unsigned: currentValue + 10 } } },
+                adminAccount,
+                dataAccount
+            );
+
+            expect(newValue).toBe(currentValue + 10);
+        }, TEST_TIMEOUT);
+
+        test("should reject a swap on a stale value", async () => {
+            const currentValue = await getCounterValue(connection, dataAccount);
+
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { CompareAndSet: { expected: { Unsigned: currentValue + 1 }, new: { Unsigned: 0 } } },
+                    adminAccount,
+                    dataAccount
+                )
+            ).rejects.toThrow(customError(CounterError.ValueMismatch));
+            expect(await getCounterValue(connection, dataAccount)).toBe(currentValue);
+        }, TEST_TIMEOUT);
+
+        test("should swap when the expected version matches", async () => {
+            const { version } = await getCounter(connection, dataAccount);
+
+            const newValue = await executeCounterInstruction(
+                connection,
+                { CompareVersionAndSet: { version, new: { Unsigned: 77 } } },
+                adminAccount,
+                dataAccount
+            );
+
+            expect(newValue).toBe(77);
+            expect((await getCounter(connection, dataAccount)).version).toBe(version + 1n);
+        }, TEST_TIMEOUT);
+
+        test("should reject a swap on a stale version", async () => {
+            const { version } = await getCounter(connection, dataAccount);
+            await executeCounterInstruction(connection, { Increment: 1 }, adminAccount, dataAccount);
+
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { CompareVersionAndSet: { version, new: { Unsigned: 0 } } },
+                    adminAccount,
+                    dataAccount
+                )
+            ).rejects.toThrow(customError(CounterError.VersionMismatch));
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Batch", () => {
+        let first: PublicKey;
+        let second: PublicKey;
+
+        beforeAll(async () => {
+            first = await initializeCounter(connection, adminAccount, new TextEncoder().encode("batch-1"));
+            second = await initializeCounter(connection, adminAccount, new TextEncoder().encode("batch-2"));
+        });
+
+        test("should apply every op across counters in one instruction", async () => {
+            const events = await sendAndCollectEvents(
+                connection,
+                batchInstruction([first, second], adminAccount.publicKey, [
+                    { Increment: { counter: 0, amount: 5 } },
+                    { Increment: { counter: 1, amount: 3 } },
+                    { Decrement: { counter: 0, amount: 2 } },
+                    { Set: { counter: 1, value: { Unsigned: 10 } } },
+                ]),
+                [adminAccount]
+            );
+
+            const firstCounter = await getCounter(connection, first);
+            const secondCounter = await getCounter(connection, second);
+            expect(counterValueToNumber(firstCounter.count)).toBe(3);
+            expect(counterValueToNumber(secondCounter.count)).toBe(10);
+            expect(firstCounter.version).toBe(1n);
+            expect(secondCounter.version).toBe(1n);
+            expect(events.map((event) => Object.keys(event)[0])).toEqual([
+                "Incremented",
+                "Incremented",
+                "Decremented",
+                "ValueSet",
+            ]);
+        }, TEST_TIMEOUT);
+
+        test("should leave every counter untouched when a later op fails", async () => {
+            await executeCounterInstruction(
+                connection,
+                { SetBounds: { mode: { Reject: {} }, min: { Unsigned: 0 }, max: { Unsigned: 12 } } },
+                adminAccount,
+                second
+            );
+
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(
+                        batchInstruction([first, second], adminAccount.publicKey, [
+                            { Increment: { counter: 0, amount: 1 } },
+                            { Increment: { counter: 1, amount: 3 } },
+                        ])
+                    ),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.OutOfBounds));
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(
+                        batchInstruction([first], adminAccount.publicKey, [
+                            { Increment: { counter: 0, amount: 1 } },
+                            { Decrement: { counter: 0, amount: 10 } },
+                        ])
+                    ),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.Underflow));
+
+            expect(await getCounterValue(connection, first)).toBe(3);
+            expect(await getCounterValue(connection, second)).toBe(10);
+        }, TEST_TIMEOUT);
+
+        test("should reject duplicate counters and out of range ops", async () => {
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(
+                        batchInstruction([first, first], adminAccount.publicKey, [
+                            { Increment: { counter: 0, amount: 1 } },
+                        ])
+                    ),
+                    [adminAccount]
+                )
+            ).rejects.toThrow();
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(
+                        batchInstruction([first], adminAccount.publicKey, [
+                            { Increment: { counter: 1, amount: 1 } },
+                        ])
+                    ),
+                    [adminAccount]
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+
+        test("should reject a batch not signed by the counters' authority", async () => {
+            const stranger = Keypair.generate();
+            await transferSol(connection, 0.1, stranger.publicKey);
+
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(
+                        batchInstruction([first], stranger.publicKey, [
+                            { Increment: { counter: 0, amount: 1 } },
+                        ])
+                    ),
+                    [stranger]
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Multi-account Updates", () => {
+        let scopes: PublicKey[];
+
+        beforeAll(async () => {
+            scopes = [];
+            for (const scope of ["global", "team", "user"]) {
+                scopes.push(await initializeCounter(connection, adminAccount, new TextEncoder().encode(scope)));
+            }
+        });
+
+        test("should apply the same delta to every counter", async () => {
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(
+                    multiCounterInstruction({ UpdateMany: { Same: { Increment: 4 } } }, scopes, adminAccount.publicKey)
+                ),
+                [adminAccount]
+            );
+
+            for (const scope of scopes) {
+                expect(await getCounterValue(connection, scope)).toBe(4);
+            }
+        }, TEST_TIMEOUT);
+
+        test("should apply per-account deltas", async () => {
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(
+                    multiCounterInstruction(
+                        { UpdateMany: { PerAccount: [{ Increment: 10 }, { Decrement: 1 }, { Increment: 0 }] } },
+                        scopes,
+                        adminAccount.publicKey
+                    )
+                ),
+                [adminAccount]
+            );
+
+            expect(await getCounterValue(connection, scopes[0]!)).toBe(14);
+            expect(await getCounterValue(connection, scopes[1]!)).toBe(3);
+            expect(await getCounterValue(connection, scopes[2]!)).toBe(4);
+        }, TEST_TIMEOUT);
+
+        test("should update no counter when one of them fails", async () => {
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(
+                        multiCounterInstruction({ UpdateMany: { Same: { Decrement: 4 } } }, scopes, adminAccount.publicKey)
+                    ),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.Underflow));
+
+            expect(await getCounterValue(connection, scopes[0]!)).toBe(14);
+            expect(await getCounterValue(connection, scopes[2]!)).toBe(4);
+        }, TEST_TIMEOUT);
+
+        test("should reject a delta count that does not match the counters", async () => {
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(
+                        multiCounterInstruction(
+                            { UpdateMany: { PerAccount: [{ Increment: 1 }] } },
+                            scopes,
+                            adminAccount.publicKey
+                        )
+                    ),
+                    [adminAccount]
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Transfer", () => {
+        let source: PublicKey;
+        let destination: PublicKey;
+
+        beforeAll(async () => {
+            source = await initializeCounter(connection, adminAccount, new TextEncoder().encode("points-a"));
+            destination = await initializeCounter(connection, adminAccount, new TextEncoder().encode("points-b"));
+            await executeCounterInstruction(connection, { Increment: 10 }, adminAccount, source);
+        });
+
+        test("should move value without changing the total", async () => {
+            const events = await sendAndCollectEvents(
+                connection,
+                transferInstruction(source, destination, adminAccount.publicKey, 4),
+                [adminAccount]
+            );
+
+            expect(await getCounterValue(connection, source)).toBe(6);
+            expect(await getCounterValue(connection, destination)).toBe(4);
+            expect(events.map((event) => Object.keys(event)[0])).toEqual(["Decremented", "Incremented"]);
+        }, TEST_TIMEOUT);
+
+        test("should reject moving more than the source holds", async () => {
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(transferInstruction(source, destination, adminAccount.publicKey, 7)),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.Underflow));
+        }, TEST_TIMEOUT);
+
+        test("should reject transfers a bound would clamp", async () => {
+            await executeCounterInstruction(
+                connection,
+                { SetBounds: { mode: { Clamp: {} }, min: { Unsigned: 0 }, max: { Unsigned: 5 } } },
+                adminAccount,
+                destination
+            );
+
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(transferInstruction(source, destination, adminAccount.publicKey, 3)),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.TotalNotConserved));
+            expect(await getCounterValue(connection, source)).toBe(6);
+            expect(await getCounterValue(connection, destination)).toBe(4);
+        }, TEST_TIMEOUT);
+
+        test("should require the source authority's signature", async () => {
+            const stranger = Keypair.generate();
+            await transferSol(connection, 0.1, stranger.publicKey);
+
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(transferInstruction(source, destination, stranger.publicKey, 1)),
+                    [stranger]
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+
+        test("should require the destination authority's signature too", async () => {
+            const owner = Keypair.generate();
+            await transferSol(connection, 0.1, owner.publicKey);
+            const theirs = await initializeCounter(connection, owner, new TextEncoder().encode("points-theirs"));
+
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(transferInstruction(source, theirs, adminAccount.publicKey, 1)),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.MissingRole));
+            expect(await getCounterValue(connection, source)).toBe(6);
+            expect(await getCounterValue(connection, theirs)).toBe(0);
+        }, TEST_TIMEOUT);
+
+        test("should reject transfers between counters of different kinds", async () => {
+            const signed = await initializeCounter(
+                connection,
+                adminAccount,
+                new TextEncoder().encode("points-signed"),
+                undefined,
+                { Signed: {} }
+            );
+
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(transferInstruction(source, signed, adminAccount.publicKey, 1)),
+                    [adminAccount]
+                )
+            ).rejects.toThrow(customError(CounterError.KindMismatch));
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Migration", () => {
+        let legacyAccount: Keypair;
+
+        beforeAll(async () => {
+            legacyAccount = Keypair.generate();
+            await createProgramAccount(connection, adminAccount, legacyAccount, LEGACY_COUNTER_SIZE, PROGRAM_ID);
+        });
+
+        test("should require the legacy counter to sign", async () => {
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(migrateCounterInstruction(legacyAccount.publicKey, adminAccount.publicKey, false)),
+                    [adminAccount]
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+
+        test("should migrate a legacy counter into the current layout", async () => {
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(migrateCounterInstruction(legacyAccount.publicKey, adminAccount.publicKey, true)),
+                [adminAccount, legacyAccount]
+            );
+
+            const accountInfo = await connection.getAccountInfo(legacyAccount.publicKey);
+            expect(accountInfo!.data.length).toBe(COUNTER_SIZE);
+            expect(accountInfo!.lamports).toBeGreaterThanOrEqual(
+                await connection.getMinimumBalanceForRentExemption(COUNTER_SIZE)
+            );
+
+            const counter = await getCounter(connection, legacyAccount.publicKey);
+            expect(counter.layoutVersion).toBe(LAYOUT_VERSION);
+            expect(counter.count).toEqual({ Unsigned: 0n });
+            expect(new PublicKey(counter.authority).equals(adminAccount.publicKey)).toBe(true);
+
+            const newValue = await executeCounterInstruction(connection, { Increment: 3 }, adminAccount, legacyAccount.publicKey);
+            expect(newValue).toBe(3);
+        }, TEST_TIMEOUT);
+
+        test("should be a no-op on an already migrated counter", async () => {
+            const before = await connection.getAccountInfo(legacyAccount.publicKey);
+
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(migrateCounterInstruction(legacyAccount.publicKey, adminAccount.publicKey, false)),
+                [adminAccount]
+            );
+
+            const after = await connection.getAccountInfo(legacyAccount.publicKey);
+            expect(after!.data.equals(before!.data)).toBe(true);
+            expect(after!.lamports).toBe(before!.lamports);
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Closing", () => {
+        test("should close a counter and return its lamports", async () => {
+            const closable = await initializeCounter(connection, adminAccount, new TextEncoder().encode("close"));
+            const destination = Keypair.generate().publicKey;
+            const counterLamports = (await connection.getAccountInfo(closable))!.lamports;
+
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(closeCounterInstruction(closable, adminAccount.publicKey, destination)),
+                [adminAccount]
+            );
+
+            expect(await connection.getAccountInfo(closable)).toBeNull();
+            expect(await connection.getBalance(destination)).toBe(counterLamports);
+        }, TEST_TIMEOUT);
+
+        test("should reject closing by a non-authority", async () => {
+            const stranger = Keypair.generate();
+            await transferSol(connection, 0.1, stranger.publicKey);
+
+            await expect(
+                sendAndConfirmTransaction(
+                    connection,
+                    new Transaction().add(closeCounterInstruction(dataAccount, stranger.publicKey, stranger.publicKey)),
+                    [stranger]
+                )
+            ).rejects.toThrow();
+            expect(await connection.getAccountInfo(dataAccount)).not.toBeNull();
+        }, TEST_TIMEOUT);
+
+        test("should not revive a counter refunded in the same transaction", async () => {
+            const seed = new TextEncoder().encode("revive");
+            const closable = await initializeCounter(connection, adminAccount, seed);
+            await executeCounterInstruction(connection, { Increment: 7 }, adminAccount, closable);
+            const rentExempt = await connection.getMinimumBalanceForRentExemption(COUNTER_SIZE);
+
+            await sendAndConfirmTransaction(
+                connection,
+                new Transaction().add(
+                    closeCounterInstruction(closable, adminAccount.publicKey, adminAccount.publicKey),
+                    SystemProgram.transfer({
+                        fromPubkey: adminAccount.publicKey,
+                        toPubkey: closable,
+                        lamports: rentExempt,
+                    })
+                ),
+                [adminAccount]
+            );
+
+            const revived = await connection.getAccountInfo(closable);
+            expect(revived!.owner.equals(SystemProgram.programId)).toBe(true);
+            expect(revived!.data.length).toBe(0);
+            await expect(
+                executeCounterInstruction(connection, { Increment: 1 }, adminAccount, closable)
+            ).rejects.toThrow();
+
+            // The address can be initialized again from scratch
+            await initializeCounter(connection, adminAccount, seed);
+            expect(await getCounterValue(connection, closable)).toBe(0);
+        }, TEST_TIMEOUT);
+    });
+
+    describe("Edge Cases and Error Handling", () => {
+        test("should handle maximum u32 increment", async () => {
+            // Reset to a known state first
+            const currentValue = await getCounterValue(connection, dataAccount);
+            
+            // Set counter to a value that allows for large increment without overflow
+            const maxU32 = 4294967295; // 2^32 - 1
+            const targetValue = 1000; // Set to a reasonable value for testing
+            
+            if (currentValue !== targetValue) {
+                if (currentValue < targetValue) {
+                    await executeCounterInstruction(
+                        connection,
+                        { Increment: targetValue - currentValue },
+                        adminAccount,
+                        dataAccount
+                    );
+                } else {
+                    await executeCounterInstruction(
+                        connection,
+                        { Decrement: currentValue - targetValue },
+                        adminAccount,
+                        dataAccount
+                    );
+                }
+            }
+            
+            // Now test large increment
+            const largeIncrement = 1000000;
+            const newValue = await executeCounterInstruction(
+                connection,
+                { Increment: largeIncrement },
+                adminAccount,
+                dataAccount
+            );
+            
+            expect(newValue).toBe(targetValue + largeIncrement);
+        }, TEST_TIMEOUT);
+
+        test("should reject an increment that overflows u64", async () => {
+            const currentValue = await getCounterValue(connection, dataAccount);
+
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { Increment: 2n ** 64n - 1n },
+                    adminAccount,
+                    dataAccount
+                )
+            ).rejects.toThrow(customError(CounterError.Overflow));
+            expect(await getCounterValue(connection, dataAccount)).toBe(currentValue);
+        }, TEST_TIMEOUT);
+
+        test("should reject a decrement below zero", async () => {
+            const currentValue = await getCounterValue(connection, dataAccount);
+
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { Decrement: currentValue + 1 },
+                    adminAccount,
+                    dataAccount
+                )
+            ).rejects.toThrow(customError(CounterError.Underflow));
+            expect(await getCounterValue(connection, dataAccount)).toBe(currentValue);
+        }, TEST_TIMEOUT);
+
+        test("should handle invalid account data gracefully", async () => {
+            const nonExistentAccount = Keypair.generate();
+            const accountInfo = await connection.getAccountInfo(nonExistentAccount.publicKey);
+            expect(accountInfo).toBeNull();
+        });
+
+        test("should fail when trying to execute instruction on non-existent account", async () => {
+            const nonExistentAccount = Keypair.generate();
+            
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { Increment: 1 },
+                    adminAccount,
+                    nonExistentAccount.publicKey
+                )
+            ).rejects.toThrow();
+        }, TEST_TIMEOUT);
+
+        test("should reject a counter account not owned by the program", async () => {
+            const foreignAccount = Keypair.generate();
+            await createProgramAccount(
+                connection,
+                adminAccount,
+                foreignAccount,
+                COUNTER_SIZE,
+                SystemProgram.programId
+            );
+
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { Increment: 1 },
+                    adminAccount,
+                    foreignAccount.publicKey
+                )
+            ).rejects.toThrow("Provided owner is not allowed");
+        }, TEST_TIMEOUT);
+
+        test("should reject an executable counter account", async () => {
+            await expect(
+                executeCounterInstruction(
+                    connection,
+                    { Increment: 1 },
+                    adminAccount,
+                    SystemProgram.programId
+                )
+            ).rejects.toThrow("invalid account data for instruction");
+        }, TEST_TIMEOUT);
+
+        test("should reject a read-only counter account", async () => {
+            const currentValue = await getCounterValue(connection, dataAccount);
+
+            const ix = new TransactionInstruction({
+                keys: [
+                    { pubkey: dataAccount, isSigner: false, isWritable: false },
+                    { pubkey: adminAccount.publicKey, isSigner: true, isWritable: false },
+                ],
+                programId: PROGRAM_ID,
+                data: serializeInstruction({ Increment: 1 }),
+            });
+
+            await expect(
+                sendAndConfirmTransaction(connection, new Transaction().add(ix), [adminAccount])
+            ).rejects.toThrow("Account is immutable");
+            expect(await getCounterValue(connection, dataAccount)).toBe(currentValue);
         }, TEST_TIMEOUT);
 
         test("should maintain consistency across rapid operations", async () => {
-            const initialValue = await getCounterValue(connection, dataAccount.publicKey);
+            const initialValue = await getCounterValue(connection, dataAccount);
             
             // Perform rapid operations
             const operations = [
@@ -425,7 +2304,7 @@ describe("Counter Program Tests", () => {
                     connection,
                     op,
                     adminAccount,
-                    dataAccount.publicKey
+                    dataAccount
                 );
                 
                 expect(actualValue).toBe(expectedValue);
@@ -438,13 +2317,13 @@ describe("Counter Program Tests", () => {
                 connection,
                 { Increment: 42 },
                 adminAccount,
-                dataAccount.publicKey
+                dataAccount
             );
             
             // Read the value multiple times to ensure consistency
-            const value1 = await getCounterValue(connection, dataAccount.publicKey);
-            const value2 = await getCounterValue(connection, dataAccount.publicKey);
-            const value3 = await getCounterValue(connection, dataAccount.publicKey);
+            const value1 = await getCounterValue(connection, dataAccount);
+            const value2 = await getCounterValue(connection, dataAccount);
+            const value3 = await getCounterValue(connection, dataAccount);
             
             expect(value1).toBe(value2);
             expect(value2).toBe(value3);
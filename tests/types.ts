@@ -1,29 +1,373 @@
 import * as borsh from "borsh";
 
+const pubkeySchema: borsh.Schema = { array: { type: 'u8', len: 32 } };
+
+export type CounterKind = { Unsigned: {} } | { Signed: {} };
+
+export type OverflowPolicy = { Checked: {} } | { Saturating: {} } | { Wrapping: {} };
+
+export type CounterValue = { Unsigned: bigint | number } | { Signed: bigint | number };
+
+export const counterKindSchema: borsh.Schema = {
+    enum: [
+        { struct: { Unsigned: { struct: {} } } },
+        { struct: { Signed: { struct: {} } } }
+    ]
+}
+
+export const overflowPolicySchema: borsh.Schema = {
+    enum: [
+        { struct: { Checked: { struct: {} } } },
+        { struct: { Saturating: { struct: {} } } },
+        { struct: { Wrapping: { struct: {} } } }
+    ]
+}
+
+export const counterValueSchema: borsh.Schema = {
+    enum: [
+        { struct: { Unsigned: 'u64' } },
+        { struct: { Signed: 'i64' } }
+    ]
+}
+
+export type AuthorityMode = { Authority: {} } | { Open: {} } | { Immutable: {} };
+
+export const authorityModeSchema: borsh.Schema = {
+    enum: [
+        { struct: { Authority: { struct: {} } } },
+        { struct: { Open: { struct: {} } } },
+        { struct: { Immutable: { struct: {} } } }
+    ]
+}
+
+export type Role = { Admin: {} } | { Incrementer: {} } | { Decrementer: {} };
+
+export const roleSchema: borsh.Schema = {
+    enum: [
+        { struct: { Admin: { struct: {} } } },
+        { struct: { Incrementer: { struct: {} } } },
+        { struct: { Decrementer: { struct: {} } } }
+    ]
+}
+
+export type BoundsMode = { Unbounded: {} } | { Reject: {} } | { Clamp: {} };
+
+export type Bounds = { mode: BoundsMode, min: CounterValue, max: CounterValue };
+
+export const boundsSchema: borsh.Schema = {
+    struct: {
+        mode: {
+            enum: [
+                { struct: { Unbounded: { struct: {} } } },
+                { struct: { Reject: { struct: {} } } },
+                { struct: { Clamp: { struct: {} } } }
+            ]
+        },
+        min: counterValueSchema,
+        max: counterValueSchema
+    }
+}
+
+/**
+ * Converts a counter value to a JS number, for values known to be small
+ * @param value - The counter value
+ * @returns The value as a number
+ */
+export function counterValueToNumber(value: CounterValue): number {
+    return Number('Unsigned' in value ? value.Unsigned : value.Signed);
+}
+
 export class CounterAccount {
-    count: number;
+    discriminator: Uint8Array;
+    layoutVersion: number;
+    bump: number;
+    count: CounterValue;
+    authority: Uint8Array;
+    version: bigint;
+    policy: OverflowPolicy;
+    bounds: Bounds;
+    monotonic: boolean;
+    pendingAuthority: Uint8Array;
+    authorityMode: AuthorityMode;
+    reserved: Uint8Array;
 
-    constructor({ count }: { count: number }) {
+    constructor({ discriminator, layoutVersion, bump, count, authority, version, policy, bounds, monotonic, pendingAuthority, authorityMode, reserved }: {
+        discriminator: Uint8Array,
+        layoutVersion: number,
+        bump: number,
+        count: CounterValue,
+        authority: Uint8Array,
+        version: bigint,
+        policy: OverflowPolicy,
+        bounds: Bounds,
+        monotonic: boolean,
+        pendingAuthority: Uint8Array,
+        authorityMode: AuthorityMode,
+        reserved: Uint8Array
+    }) {
+        this.discriminator = discriminator;
+        this.layoutVersion = layoutVersion;
+        this.bump = bump;
         this.count = count;
+        this.authority = authority;
+        this.version = version;
+        this.policy = policy;
+        this.bounds = bounds;
+        this.monotonic = monotonic;
+        this.pendingAuthority = pendingAuthority;
+        this.authorityMode = authorityMode;
+        this.reserved = reserved;
     }
 }
 
+export type Op =
+  | { Increment: { counter: number, amount: bigint | number } }
+  | { Decrement: { counter: number, amount: bigint | number } }
+  | { Set: { counter: number, value: CounterValue } };
+
+export const opSchema: borsh.Schema = {
+    enum: [
+        { struct: { Increment: { struct: { counter: 'u8', amount: 'u64' } } } },
+        { struct: { Decrement: { struct: { counter: 'u8', amount: 'u64' } } } },
+        { struct: { Set: { struct: { counter: 'u8', value: counterValueSchema } } } }
+    ]
+}
+
+export type Delta = { Increment: bigint | number } | { Decrement: bigint | number };
+
+export type Deltas = { Same: Delta } | { PerAccount: Delta[] };
+
+export const deltaSchema: borsh.Schema = {
+    enum: [
+        { struct: { Increment: 'u64' } },
+        { struct: { Decrement: 'u64' } }
+    ]
+}
+
+export const deltasSchema: borsh.Schema = {
+    enum: [
+        { struct: { Same: deltaSchema } },
+        { struct: { PerAccount: { array: { type: deltaSchema } } } }
+    ]
+}
+
 export type CounterInstruction =
-  | { Increment: number }
-  | { Decrement: number };
+  | { Increment: bigint | number }
+  | { Decrement: bigint | number }
+  | { Initialize: { seed: Uint8Array, kind: CounterKind, policy: OverflowPolicy, monotonic: boolean } }
+  | { Close: {} }
+  | { Reset: {} }
+  | { Set: CounterValue }
+  | { CompareAndSet: { expected: CounterValue, new: CounterValue } }
+  | { CompareVersionAndSet: { version: bigint | number, new: CounterValue } }
+  | { Migrate: {} }
+  | { SetBounds: Bounds }
+  | { Get: {} }
+  | { Batch: Op[] }
+  | { UpdateMany: Deltas }
+  | { Transfer: { amount: bigint | number } }
+  | { ProposeAuthority: Uint8Array }
+  | { AcceptAuthority: {} }
+  | { CancelAuthorityTransfer: {} }
+  | { RenounceAuthority: AuthorityMode }
+  | { GrantRole: { member: Uint8Array, role: Role } }
+  | { RevokeRole: { member: Uint8Array, role: Role } }
+  | { SetMultisig: { signers: Uint8Array[], threshold: number } };
 
 
 export const instructionSchema: borsh.Schema = {
     enum: [
-        { struct: { Increment: 'u32' } },
-        { struct: { Decrement: 'u32' } }
+        { struct: { Increment: 'u64' } },
+        { struct: { Decrement: 'u64' } },
+        { struct: { Initialize: { struct: { seed: { array: { type: 'u8' } }, kind: counterKindSchema, policy: overflowPolicySchema, monotonic: 'bool' } } } },
+        { struct: { Close: { struct: {} } } },
+        { struct: { Reset: { struct: {} } } },
+        { struct: { Set: counterValueSchema } },
+        { struct: { CompareAndSet: { struct: { expected: counterValueSchema, new: counterValueSchema } } } },
+        { struct: { CompareVersionAndSet: { struct: { version: 'u64', new: counterValueSchema } } } },
+        { struct: { Migrate: { struct: {} } } },
+        { struct: { SetBounds: boundsSchema } },
+        { struct: { Get: { struct: {} } } },
+        { struct: { Batch: { array: { type: opSchema } } } },
+        { struct: { UpdateMany: deltasSchema } },
+        { struct: { Transfer: { struct: { amount: 'u64' } } } },
+        { struct: { ProposeAuthority: pubkeySchema } },
+        { struct: { AcceptAuthority: { struct: {} } } },
+        { struct: { CancelAuthorityTransfer: { struct: {} } } },
+        { struct: { RenounceAuthority: authorityModeSchema } },
+        { struct: { GrantRole: { struct: { member: pubkeySchema, role: roleSchema } } } },
+        { struct: { RevokeRole: { struct: { member: pubkeySchema, role: roleSchema } } } },
+        { struct: { SetMultisig: { struct: { signers: { array: { type: pubkeySchema } }, threshold: 'u8' } } } }
     ]
 }
 
+/** Return data of every instruction that updates a counter, and of `Get` */
+export type CounterReturnData = { previous: CounterValue, current: CounterValue, version: bigint };
+
+export const returnDataSchema: borsh.Schema = {
+    struct: {
+        previous: counterValueSchema,
+        current: counterValueSchema,
+        version: 'u64'
+    }
+}
+
+export const COUNTER_DISCRIMINATOR = new TextEncoder().encode("counter\0");
+export const LAYOUT_VERSION = 2;
+export const RESERVED_LEN = 74;
+export const LEGACY_COUNTER_SIZE = 4;
+
 export const schema: borsh.Schema = {
     struct: {
-        count: 'u32'
+        discriminator: { array: { type: 'u8', len: 8 } },
+        layoutVersion: 'u8',
+        bump: 'u8',
+        count: counterValueSchema,
+        authority: { array: { type: 'u8', len: 32 } },
+        version: 'u64',
+        policy: overflowPolicySchema,
+        bounds: boundsSchema,
+        monotonic: 'bool',
+        pendingAuthority: pubkeySchema,
+        authorityMode: authorityModeSchema,
+        reserved: { array: { type: 'u8', len: RESERVED_LEN } }
     }
 }
 
-export const COUNTER_SIZE = borsh.serialize(schema, new CounterAccount({ count: 0 })).length;
\ No newline at end of file
+export const COUNTER_SEED = new TextEncoder().encode("counter");
+export const MULTISIG_SEED = new TextEncoder().encode("multisig");
+
+export enum CounterError {
+    Overflow = 0,
+    Underflow = 1,
+    ValueMismatch = 2,
+    VersionMismatch = 3,
+    InvalidAccountDiscriminator = 4,
+    UnsupportedLayoutVersion = 5,
+    KindMismatch = 6,
+    OutOfBounds = 7,
+    InvalidBounds = 8,
+    MonotonicViolation = 9,
+    TotalNotConserved = 10,
+    NoPendingAuthority = 11,
+    ImmutableCounter = 12,
+    NoAuthority = 13,
+    MissingRole = 14,
+    RoleTableFull = 15,
+    InvalidMultisig = 16,
+    NotEnoughSigners = 17,
+    MonotonicClose = 18,
+}
+
+export const counterErrorMessages: Record<CounterError, string> = {
+    [CounterError.Overflow]: "Counter overflowed",
+    [CounterError.Underflow]: "Counter underflowed",
+    [CounterError.ValueMismatch]: "Counter value does not match the expected value",
+    [CounterError.VersionMismatch]: "Counter version does not match the expected version",
+    [CounterError.InvalidAccountDiscriminator]: "Account is not a counter",
+    [CounterError.UnsupportedLayoutVersion]: "Counter account layout version is not supported",
+    [CounterError.KindMismatch]: "Value does not match the counter's kind",
+    [CounterError.OutOfBounds]: "Counter value is outside its bounds",
+    [CounterError.InvalidBounds]: "Counter bounds are invalid",
+    [CounterError.MonotonicViolation]: "Monotonic counter cannot decrease",
+    [CounterError.TotalNotConserved]: "Transfer would change the total of the counters",
+    [CounterError.NoPendingAuthority]: "Counter has no pending authority transfer",
+    [CounterError.ImmutableCounter]: "Counter is immutable",
+    [CounterError.NoAuthority]: "Counter authority has been renounced",
+    [CounterError.MissingRole]: "Signer does not hold the role this instruction requires",
+    [CounterError.RoleTableFull]: "Counter has no room for another role member",
+    [CounterError.InvalidMultisig]: "Multisig signers or threshold are invalid",
+    [CounterError.NotEnoughSigners]: "Multisig authority is missing required signatures",
+    [CounterError.MonotonicClose]: "Monotonic counter cannot be closed",
+};
+
+export const COUNTER_SIZE = borsh.serialize(schema, new CounterAccount({
+    discriminator: COUNTER_DISCRIMINATOR,
+    layoutVersion: LAYOUT_VERSION,
+    bump: 0,
+    count: { Unsigned: 0 },
+    authority: new Uint8Array(32),
+    version: 0n,
+    policy: { Checked: {} },
+    bounds: { mode: { Unbounded: {} }, min: { Unsigned: 0 }, max: { Unsigned: 0 } },
+    monotonic: false,
+    pendingAuthority: new Uint8Array(32),
+    authorityMode: { Authority: {} },
+    reserved: new Uint8Array(RESERVED_LEN)
+})).length;
+
+/** Size of one entry of the role table that follows the counter */
+export const ROLE_ENTRY_SIZE = 33;
+
+/** Bit of each role in a role table entry */
+export const ROLE_BITS = { Admin: 1, Incrementer: 2, Decrementer: 4 };
+
+export type RoleEntry = { member: Uint8Array, roles: number };
+
+/**
+ * Decodes the role table stored after the counter
+ * @param data - Data of the counter account
+ * @returns The entries, including those left without roles
+ */
+export function parseRoleEntries(data: Uint8Array): RoleEntry[] {
+    const entries: RoleEntry[] = [];
+    for (let offset = COUNTER_SIZE; offset + ROLE_ENTRY_SIZE <= data.length; offset += ROLE_ENTRY_SIZE) {
+        entries.push({ member: data.slice(offset, offset + 32), roles: data[offset + 32]! });
+    }
+    return entries;
+}
+
+export const EVENT_DISCRIMINATOR = new TextEncoder().encode("cntr_evt");
+
+export type CounterEvent =
+  | { CounterInitialized: { counter: Uint8Array, authority: Uint8Array, kind: CounterKind } }
+  | { Incremented: { counter: Uint8Array, amount: bigint, previous: CounterValue, current: CounterValue } }
+  | { Decremented: { counter: Uint8Array, amount: bigint, previous: CounterValue, current: CounterValue } }
+  | { Reset: { counter: Uint8Array, previous: CounterValue, current: CounterValue } }
+  | { AuthorityChanged: { counter: Uint8Array, previous: Uint8Array, new: Uint8Array } }
+  | { Closed: { counter: Uint8Array, destination: Uint8Array, lamports: bigint } }
+  | { ValueSet: { counter: Uint8Array, previous: CounterValue, current: CounterValue } }
+  | { AuthorityProposed: { counter: Uint8Array, authority: Uint8Array, pending: Uint8Array } }
+  | { AuthorityTransferCancelled: { counter: Uint8Array, pending: Uint8Array } }
+  | { AuthorityRenounced: { counter: Uint8Array, previous: Uint8Array, mode: AuthorityMode } }
+  | { RoleGranted: { counter: Uint8Array, member: Uint8Array, role: Role } }
+  | { RoleRevoked: { counter: Uint8Array, member: Uint8Array, role: Role } }
+  | { MultisigSet: { counter: Uint8Array, multisig: Uint8Array, signers: Uint8Array[], threshold: number } };
+
+export const eventSchema: borsh.Schema = {
+    enum: [
+        { struct: { CounterInitialized: { struct: { counter: pubkeySchema, authority: pubkeySchema, kind: counterKindSchema } } } },
+        { struct: { Incremented: { struct: { counter: pubkeySchema, amount: 'u64', previous: counterValueSchema, current: counterValueSchema } } } },
+        { struct: { Decremented: { struct: { counter: pubkeySchema, amount: 'u64', previous: counterValueSchema, current: counterValueSchema } } } },
+        { struct: { Reset: { struct: { counter: pubkeySchema, previous: counterValueSchema, current: counterValueSchema } } } },
+        { struct: { AuthorityChanged: { struct: { counter: pubkeySchema, previous: pubkeySchema, new: pubkeySchema } } } },
+        { struct: { Closed: { struct: { counter: pubkeySchema, destination: pubkeySchema, lamports: 'u64' } } } },
+        { struct: { ValueSet: { struct: { counter: pubkeySchema, previous: counterValueSchema, current: counterValueSchema } } } },
+        { struct: { AuthorityProposed: { struct: { counter: pubkeySchema, authority: pubkeySchema, pending: pubkeySchema } } } },
+        { struct: { AuthorityTransferCancelled: { struct: { counter: pubkeySchema, pending: pubkeySchema } } } },
+        { struct: { AuthorityRenounced: { struct: { counter: pubkeySchema, previous: pubkeySchema, mode: authorityModeSchema } } } },
+        { struct: { RoleGranted: { struct: { counter: pubkeySchema, member: pubkeySchema, role: roleSchema } } } },
+        { struct: { RoleRevoked: { struct: { counter: pubkeySchema, member: pubkeySchema, role: roleSchema } } } },
+        { struct: { MultisigSet: { struct: { counter: pubkeySchema, multisig: pubkeySchema, signers: { array: { type: pubkeySchema } }, threshold: 'u8' } } } }
+    ]
+}
+
+/**
+ * Decodes the counter events from a transaction's log messages
+ * @param logs - Log messages of the transaction
+ * @returns The events, in emission order
+ */
+export function parseCounterEvents(logs: string[]): CounterEvent[] {
+    const events: CounterEvent[] = [];
+    for (const line of logs) {
+        if (!line.startsWith("Program data: ")) {
+            continue;
+        }
+        const fields = line.slice("Program data: ".length).split(" ").map((field) => Buffer.from(field, "base64"));
+        const [discriminator, event] = fields;
+        if (fields.length === 2 && discriminator!.equals(Buffer.from(EVENT_DISCRIMINATOR))) {
+            events.push(borsh.deserialize(eventSchema, event!) as CounterEvent);
+        }
+    }
+    return events;
+}